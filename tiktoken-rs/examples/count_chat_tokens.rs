@@ -5,17 +5,17 @@ fn main() -> Result<(), Box<dyn Error>> {
     let model = "gpt-4";
     let messages = vec![
         ChatCompletionRequestMessage {
-            content: Some("You are a helpful assistant that only speaks French.".to_string()),
+            content: Some("You are a helpful assistant that only speaks French.".into()),
             role: "system".to_string(),
             ..Default::default()
         },
         ChatCompletionRequestMessage {
-            content: Some("Hello, how are you?".to_string()),
+            content: Some("Hello, how are you?".into()),
             role: "user".to_string(),
             ..Default::default()
         },
         ChatCompletionRequestMessage {
-            content: Some("Parlez-vous francais?".to_string()),
+            content: Some("Parlez-vous francais?".into()),
             role: "system".to_string(),
             ..Default::default()
         },
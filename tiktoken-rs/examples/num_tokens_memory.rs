@@ -19,12 +19,12 @@ fn main() -> Result<(), Box<dyn Error>> {
     let messages = vec![
         ChatCompletionRequestMessage {
             role: "system".to_string(),
-            content: Some(content),
+            content: Some(content.into()),
             ..Default::default()
         },
         ChatCompletionRequestMessage {
             role: "user".to_string(),
-            content: Some("We don't have time to boil the ocean.".to_string()),
+            content: Some("We don't have time to boil the ocean.".into()),
             ..Default::default()
         },
     ];
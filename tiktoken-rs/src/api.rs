@@ -1,8 +1,18 @@
 use anyhow::{anyhow, Result};
 
+#[cfg(feature = "tools")]
+use crate::tools::{
+    count_function_definitions, count_response_format, FunctionDefinition, ResponseFormat,
+};
 use crate::{
     cl100k_base_singleton,
-    model::get_context_size,
+    counter::TokenCounter,
+    model::{
+        get_context_size, get_embedding_limits, get_estimation_profile, get_max_output_tokens,
+        get_model_capabilities, get_model_pricing, notify_if_deprecated, CostEstimate,
+        EmbeddingLimits, EstimationProfile, ModelPricing,
+    },
+    multimodal::{AudioInput, ContentPartEstimator, DefaultEstimator, FileInput, ImageInput},
     o200k_base_singleton, o200k_harmony_singleton, p50k_base_singleton, p50k_edit_singleton,
     r50k_base_singleton,
     tokenizer::{get_tokenizer, Tokenizer},
@@ -50,6 +60,7 @@ pub fn get_completion_max_tokens(model: &str, prompt: &str) -> Result<usize> {
 
 /// The name and arguments of a function that should be called, as generated by the model.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunctionCall {
     /// The name of the function to call.
     pub name: String,
@@ -57,22 +68,273 @@ pub struct FunctionCall {
     pub arguments: String,
 }
 
+/// The name and input of a custom tool that should be called, as generated by the model.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CustomToolCall {
+    /// The name of the custom tool to call.
+    pub name: String,
+    /// The free-form input to call the tool with, as generated by the model.
+    pub input: String,
+}
+
+/// A tool call generated by the model, as given in the `tool_calls` of an assistant message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum ToolCall {
+    /// A call to a function, with JSON arguments.
+    Function {
+        /// The ID of the tool call, which the `tool_call_id` of its result refers to.
+        id: String,
+        /// The function that the model called.
+        function: FunctionCall,
+    },
+    /// A call to a custom tool, with free-form input.
+    Custom {
+        /// The ID of the tool call, which the `tool_call_id` of its result refers to.
+        id: String,
+        /// The custom tool that the model called.
+        custom: CustomToolCall,
+    },
+}
+
+impl ToolCall {
+    /// Creates a function tool call.
+    pub fn function(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        arguments: impl Into<String>,
+    ) -> Self {
+        Self::Function {
+            id: id.into(),
+            function: FunctionCall {
+                name: name.into(),
+                arguments: arguments.into(),
+            },
+        }
+    }
+
+    /// Creates a custom tool call.
+    pub fn custom(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        input: impl Into<String>,
+    ) -> Self {
+        Self::Custom {
+            id: id.into(),
+            custom: CustomToolCall {
+                name: name.into(),
+                input: input.into(),
+            },
+        }
+    }
+
+    /// Returns the ID of the tool call.
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Function { id, .. } | Self::Custom { id, .. } => id,
+        }
+    }
+
+    /// Returns the name of the called function or custom tool.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Function { function, .. } => &function.name,
+            Self::Custom { custom, .. } => &custom.name,
+        }
+    }
+
+    /// Returns the arguments of a function call, or the input of a custom tool call.
+    pub fn arguments(&self) -> &str {
+        match self {
+            Self::Function { function, .. } => &function.arguments,
+            Self::Custom { custom, .. } => &custom.input,
+        }
+    }
+}
+
+/// A part of the content of a message, as given in a content array.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentPart {
+    /// Text. The text parts of a message are counted joined, like a string content.
+    Text(String),
+    /// A refusal generated by the model. The refusal parts of a message are counted joined,
+    /// like `refusal`.
+    Refusal(String),
+    /// An image, counted with the model's image token formula
+    /// (see [`num_tokens_from_image`](crate::multimodal::num_tokens_from_image)).
+    Image(ImageInput),
+    /// An audio clip, counted by duration
+    /// (see [`num_tokens_from_audio`](crate::multimodal::num_tokens_from_audio)).
+    Audio(AudioInput),
+    /// A file, whose extracted text and page images are counted
+    /// (see [`num_tokens_from_file`](crate::multimodal::num_tokens_from_file)).
+    File(FileInput),
+}
+
+impl From<String> for ContentPart {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<&str> for ContentPart {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+impl From<ImageInput> for ContentPart {
+    fn from(image: ImageInput) -> Self {
+        Self::Image(image)
+    }
+}
+
+impl From<AudioInput> for ContentPart {
+    fn from(audio: AudioInput) -> Self {
+        Self::Audio(audio)
+    }
+}
+
+impl From<FileInput> for ContentPart {
+    fn from(file: FileInput) -> Self {
+        Self::File(file)
+    }
+}
+
+/// The content of a message: a string, or an array of content parts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageContent {
+    /// Text content.
+    Text(String),
+    /// Content parts, such as text, images, audio clips and files, in order. Non-text parts
+    /// are counted with a [`ContentPartEstimator`].
+    Parts(Vec<ContentPart>),
+}
+
+impl MessageContent {
+    /// Returns the text of the content: the string, or the text parts joined.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tiktoken_rs::multimodal::ImageInput;
+    /// use tiktoken_rs::MessageContent;
+    ///
+    /// let content = MessageContent::Parts(vec![
+    ///     "What is ".into(),
+    ///     ImageInput::new(512, 512).into(),
+    ///     "this?".into(),
+    /// ]);
+    /// assert_eq!(content.text(), "What is this?");
+    /// ```
+    pub fn text(&self) -> std::borrow::Cow<'_, str> {
+        match self {
+            Self::Text(text) => text.into(),
+            Self::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text(text) => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<String>()
+                .into(),
+        }
+    }
+
+    /// Returns the refusal parts joined, or `None` if there are none.
+    pub(crate) fn refusal(&self) -> Option<String> {
+        let Self::Parts(parts) = self else {
+            return None;
+        };
+        let refusals: Vec<&str> = parts
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Refusal(refusal) => Some(refusal.as_str()),
+                _ => None,
+            })
+            .collect();
+        (!refusals.is_empty()).then(|| refusals.concat())
+    }
+
+    /// Returns the content parts, or an empty slice for text content.
+    pub fn parts(&self) -> &[ContentPart] {
+        match self {
+            Self::Text(_) => &[],
+            Self::Parts(parts) => parts,
+        }
+    }
+
+    /// Returns `true` if the content has images, audio clips or files.
+    pub fn has_media(&self) -> bool {
+        self.parts().iter().any(|part| {
+            matches!(
+                part,
+                ContentPart::Image(_) | ContentPart::Audio(_) | ContentPart::File(_)
+            )
+        })
+    }
+
+    /// Appends text to the string, or to the last part if it is text, or as a new text part.
+    pub(crate) fn push_str(&mut self, text: &str) {
+        match self {
+            Self::Text(content) => content.push_str(text),
+            Self::Parts(parts) => match parts.last_mut() {
+                Some(ContentPart::Text(last)) => last.push_str(text),
+                _ => parts.push(text.into()),
+            },
+        }
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<&str> for MessageContent {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+impl From<Vec<ContentPart>> for MessageContent {
+    fn from(parts: Vec<ContentPart>) -> Self {
+        Self::Parts(parts)
+    }
+}
+
+/// A message of a chat completion request.
+///
+/// With the `serde` feature, messages (de)serialize in the OpenAI wire format, with a string
+/// content as [`MessageContent::Text`] and a content array as [`MessageContent::Parts`].
+/// Images, whose dimensions are not part of the wire format, are deserialized at the highest
+/// cost of their detail, and wav audio by its duration. Files and other audio cannot be
+/// deserialized, and messages with images, audio or files cannot be serialized.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct ChatCompletionRequestMessage {
     /// The role of the messages author. One of `system`, `developer`, `user`, `assistant`, `tool`, or `function`.
     pub role: String,
-    /// The contents of the message.
+    /// The contents of the message, a string or an array of content parts.
     /// `content` is required for all messages except assistant messages with function calls.
-    pub content: Option<String>,
+    pub content: Option<MessageContent>,
     /// The name of the author of this message. `name` is required if role is function,
     /// and it should be the name of the function whose response is in the `content`.
     /// May contain a-z, A-Z, 0-9, and underscores, with a maximum length of 64 characters.
     pub name: Option<String>,
     /// The name and arguments of a function that should be called, as generated by the model.
     pub function_call: Option<FunctionCall>,
-    /// Tool calls generated by the model, represented as FunctionCall structs.
-    /// Tool call IDs and type discriminators are not preserved.
-    pub tool_calls: Vec<FunctionCall>,
+    /// Tool calls generated by the model. Their IDs are not counted, as the model does not
+    /// see them.
+    pub tool_calls: Vec<ToolCall>,
+    /// The ID of the tool call that this message responds to, for `tool` messages. It is not
+    /// counted, as the model does not see it.
+    pub tool_call_id: Option<String>,
     /// The refusal message generated by the model.
     pub refusal: Option<String>,
 }
@@ -83,6 +345,9 @@ pub struct ChatCompletionRequestMessage {
 /// the given model. This is used to estimate the number of tokens that will be used for chat
 /// completion.
 ///
+/// If the model is deprecated, the hook registered with
+/// [`set_deprecation_hook`](crate::model::set_deprecation_hook) is invoked.
+///
 /// # Arguments
 ///
 /// * model: A string slice containing the model name (e.g. "gpt-3.5").
@@ -98,74 +363,381 @@ pub struct ChatCompletionRequestMessage {
 /// This function will return an error if:
 ///
 /// * The tokenizer for the specified model is not found.
+/// * The model is known not to be a chat model (see [`get_model_capabilities`]), e.g.
+///   `davinci-002` or `text-embedding-3-small`.
 /// * The tokenizer is not a supported chat model (i.e., not one of Cl100kBase, O200kBase, or O200kHarmony).
+/// * A message has images, or files with page images, and the model does not accept image inputs.
+/// * A message has audio and the model does not accept audio inputs.
 ///
 pub fn num_tokens_from_messages(
     model: &str,
     messages: &[ChatCompletionRequestMessage],
 ) -> Result<usize> {
-    let tokenizer =
-        get_tokenizer(model).ok_or_else(|| anyhow!("No tokenizer found for model {}", model))?;
-    if tokenizer != Tokenizer::Cl100kBase
-        && tokenizer != Tokenizer::O200kBase
-        && tokenizer != Tokenizer::O200kHarmony
+    ChatCounter::new(model)?.num_tokens(messages)
+}
+
+/// The tokens of one message of a chat request, by field, as returned by
+/// [`num_tokens_breakdown_from_messages`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MessageTokens {
+    /// Tokens of the `role`.
+    pub role: usize,
+    /// Tokens of the `name`, if any.
+    pub name: usize,
+    /// Tokens of the text `content` and text content parts, if any.
+    pub content: usize,
+    /// Tokens of the names and arguments (or inputs) of the `function_call` and `tool_calls`.
+    pub tool_calls: usize,
+    /// Tokens of the `refusal` and refusal content parts, if any.
+    pub refusal: usize,
+    /// Tokens of the images, audio clips and files in the content parts.
+    pub media: usize,
+    /// Tokens the chat format adds around the message, its name and each of its calls.
+    pub framing: usize,
+}
+
+impl MessageTokens {
+    /// Returns the number of tokens of the message.
+    pub fn total(&self) -> usize {
+        self.role
+            + self.name
+            + self.content
+            + self.tool_calls
+            + self.refusal
+            + self.media
+            + self.framing
+    }
+}
+
+/// The tokens of a chat request, per message, as returned by
+/// [`num_tokens_breakdown_from_messages`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ChatTokenBreakdown {
+    /// The tokens of each message, in the same order as the messages.
+    pub messages: Vec<MessageTokens>,
+    /// Tokens added once at the end of the prompt to prime the assistant's reply.
+    pub reply_priming: usize,
+}
+
+impl ChatTokenBreakdown {
+    /// Returns the number of tokens of the request, i.e. what
+    /// [`num_tokens_from_messages`] returns.
+    pub fn total(&self) -> usize {
+        self.messages
+            .iter()
+            .map(MessageTokens::total)
+            .sum::<usize>()
+            + self.reply_priming
+    }
+}
+
+/// Like [`num_tokens_from_messages`], but returns the tokens of each message by field, so
+/// that the total can be audited.
+///
+/// # Arguments
+///
+/// * `model` - A string slice representing the model name, e.g., `"gpt-4o"`.
+/// * `messages` - A slice of `ChatCompletionRequestMessage` instances.
+///
+/// # Errors
+///
+/// Returns the same errors as [`num_tokens_from_messages`].
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::{num_tokens_breakdown_from_messages, num_tokens_from_messages, ChatCompletionRequestMessage};
+///
+/// let messages = vec![ChatCompletionRequestMessage {
+///     role: "user".to_string(),
+///     content: Some("Hello, world!".into()),
+///     ..Default::default()
+/// }];
+/// let breakdown = num_tokens_breakdown_from_messages("gpt-4o", &messages).unwrap();
+/// assert_eq!(breakdown.messages[0].content, 4);
+/// assert_eq!(breakdown.messages[0].framing, 3);
+/// assert_eq!(breakdown.reply_priming, 3);
+/// assert_eq!(breakdown.total(), num_tokens_from_messages("gpt-4o", &messages).unwrap());
+/// ```
+pub fn num_tokens_breakdown_from_messages(
+    model: &str,
+    messages: &[ChatCompletionRequestMessage],
+) -> Result<ChatTokenBreakdown> {
+    ChatCounter::new(model)?.breakdown(messages)
+}
+
+/// Like [`num_tokens_from_messages`], but counts the images, audio clips and files of the
+/// messages with the given estimator instead of the built-in formulas.
+///
+/// # Arguments
+///
+/// * `model` - A string slice representing the model name, e.g., `"gpt-4o"`.
+/// * `messages` - A slice of `ChatCompletionRequestMessage` instances.
+/// * `estimator` - Counts the non-text content parts of the messages.
+///
+/// # Errors
+///
+/// Returns the same errors as [`num_tokens_from_messages`], and the errors of the estimator.
+pub fn num_tokens_from_messages_with_estimator<E>(
+    model: &str,
+    messages: &[ChatCompletionRequestMessage],
+    estimator: &E,
+) -> Result<usize>
+where
+    E: ContentPartEstimator + ?Sized,
+{
+    Ok(ChatCounter::new(model)?
+        .breakdown_with_estimator(messages, estimator)?
+        .total())
+}
+
+// Token overhead constants adapted from the OpenAI cookbook:
+// https://github.com/openai/openai-cookbook/blob/main/examples/How_to_count_tokens_with_tiktoken.ipynb
+//
+// tokens_per_message: overhead tokens per message for framing (3 for current models)
+// tokens_per_name: extra tokens when a `name` field is present (1 for current models)
+//
+// The gpt-3.5-turbo-0301 branch (4, -1) was removed from the cookbook in later revisions;
+// we retain it for backward compatibility with that specific snapshot. Both are counted
+// as framing, which keeps each message's framing non-negative.
+//
+// FUNCTION_CALL_OVERHEAD: 1 extra token per function/tool call (heuristic)
+// REPLY_PRIMING: 3 tokens added once at the end (per cookbook: <|start|>assistant<|message|>)
+const FUNCTION_CALL_OVERHEAD: usize = 1;
+pub(crate) const REPLY_PRIMING: usize = 3;
+
+/// Counts the tokens of chat messages and requests for a model.
+///
+/// The free functions, e.g. [`num_tokens_from_messages`], count with a `ChatCounter` for the
+/// model's own tokenizer. [`ChatCounter::with_counter`] counts with any [`TokenCounter`]
+/// instead, e.g. a cache or the tokenizer of another provider's model. The model still
+/// selects the chat format, the context size and the estimates for images, audio and files,
+/// but it does not need a known tokenizer.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::{cl100k_base_singleton, ChatCompletionRequestMessage, ChatCounter};
+///
+/// let messages = vec![ChatCompletionRequestMessage {
+///     role: "user".to_string(),
+///     content: Some("Hello, world!".into()),
+///     ..Default::default()
+/// }];
+/// let counter = ChatCounter::with_counter("claude-sonnet-4", cl100k_base_singleton()).unwrap();
+/// assert_eq!(counter.num_tokens(&messages).unwrap(), 11);
+/// ```
+pub struct ChatCounter<'a, C: ?Sized = CoreBPE> {
+    model: String,
+    bpe: &'a C,
+    tokens_per_message: usize,
+    tokens_per_name: isize,
+}
+
+impl<C: ?Sized> Clone for ChatCounter<'_, C> {
+    fn clone(&self) -> Self {
+        Self {
+            model: self.model.clone(),
+            ..*self
+        }
+    }
+}
+
+impl ChatCounter<'static> {
+    /// Creates a counter that encodes with the model's tokenizer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no tokenizer is found for the model, if it is not a chat
+    /// tokenizer, or if the model is known not to be a chat model.
+    pub fn new(model: &str) -> Result<Self> {
+        let tokenizer = get_tokenizer(model)
+            .ok_or_else(|| anyhow!("No tokenizer found for model {}", model))?;
+        if tokenizer != Tokenizer::Cl100kBase
+            && tokenizer != Tokenizer::O200kBase
+            && tokenizer != Tokenizer::O200kHarmony
+        {
+            anyhow::bail!(
+                "Chat token counting is not supported for model {:?} (tokenizer {:?}). \
+                 Supported tokenizers: Cl100kBase, O200kBase, O200kHarmony.",
+                model,
+                tokenizer
+            )
+        }
+        Self::with_counter(model, bpe_singleton(tokenizer))
+    }
+}
+
+impl<'a, C: TokenCounter + ?Sized> ChatCounter<'a, C> {
+    /// Creates a counter that encodes with the given counter instead of the model's tokenizer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the model is known not to be a chat model (see
+    /// [`get_model_capabilities`]).
+    pub fn with_counter(model: &str, counter: &'a C) -> Result<Self> {
+        notify_if_deprecated(model);
+        if get_model_capabilities(model).is_some_and(|capabilities| !capabilities.chat) {
+            anyhow::bail!(
+                "Chat token counting is not supported for model {:?}: it is not a chat model.",
+                model
+            )
+        }
+        let (tokens_per_message, tokens_per_name) = if model == "gpt-3.5-turbo-0301" {
+            (4, -1)
+        } else {
+            (3, 1)
+        };
+        Ok(Self {
+            model: model.to_string(),
+            bpe: counter,
+            tokens_per_message,
+            tokens_per_name,
+        })
+    }
+
+    /// Returns the model the counter counts for.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Returns the counter that encodes text.
+    pub fn counter(&self) -> &'a C {
+        self.bpe
+    }
+
+    /// Returns the number of prompt tokens of the messages, see [`num_tokens_from_messages`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a content part cannot be counted for the model.
+    pub fn num_tokens(&self, messages: &[ChatCompletionRequestMessage]) -> Result<usize> {
+        Ok(self.breakdown(messages)?.total())
+    }
+
+    /// Returns the tokens of each message by field, see
+    /// [`num_tokens_breakdown_from_messages`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a content part cannot be counted for the model.
+    pub fn breakdown(
+        &self,
+        messages: &[ChatCompletionRequestMessage],
+    ) -> Result<ChatTokenBreakdown> {
+        self.breakdown_with_estimator(messages, &DefaultEstimator)
+    }
+
+    /// Like [`ChatCounter::breakdown`], but counts the images, audio clips and files with the
+    /// given estimator, see [`num_tokens_from_messages_with_estimator`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of the estimator.
+    pub fn breakdown_with_estimator<E>(
+        &self,
+        messages: &[ChatCompletionRequestMessage],
+        estimator: &E,
+    ) -> Result<ChatTokenBreakdown>
+    where
+        E: ContentPartEstimator + ?Sized,
     {
-        anyhow::bail!(
-            "Chat token counting is not supported for model {:?} (tokenizer {:?}). \
-             Supported tokenizers: Cl100kBase, O200kBase, O200kHarmony.",
-            model,
-            tokenizer
-        )
+        Ok(ChatTokenBreakdown {
+            messages: messages
+                .iter()
+                .map(|message| self.count_message_with_estimator(message, estimator))
+                .collect::<Result<_>>()?,
+            reply_priming: REPLY_PRIMING,
+        })
     }
-    let bpe = bpe_singleton(tokenizer);
 
-    // Token overhead constants adapted from the OpenAI cookbook:
-    // https://github.com/openai/openai-cookbook/blob/main/examples/How_to_count_tokens_with_tiktoken.ipynb
-    //
-    // tokens_per_message: overhead tokens per message for framing (3 for current models)
-    // tokens_per_name: extra tokens when a `name` field is present (1 for current models)
-    //
-    // The gpt-3.5-turbo-0301 branch (4, -1) was removed from the cookbook in later revisions;
-    // we retain it for backward compatibility with that specific snapshot.
-    //
-    // FUNCTION_CALL_OVERHEAD: 1 extra token per function/tool call (heuristic)
-    // REPLY_PRIMING: 3 tokens added once at the end (per cookbook: <|start|>assistant<|message|>)
-    const FUNCTION_CALL_OVERHEAD: i32 = 1;
-    const REPLY_PRIMING: i32 = 3;
-
-    let (tokens_per_message, tokens_per_name) = if model == "gpt-3.5-turbo-0301" {
-        (4, -1)
-    } else {
-        (3, 1)
-    };
+    /// Returns the tokens of a single message, without the reply priming.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a content part cannot be counted for the model.
+    pub fn count_message(&self, message: &ChatCompletionRequestMessage) -> Result<MessageTokens> {
+        self.count_message_with_estimator(message, &DefaultEstimator)
+    }
 
-    let mut num_tokens: i32 = 0;
-    for message in messages {
-        num_tokens += tokens_per_message;
-        num_tokens += bpe.count_with_special_tokens(&message.role) as i32;
+    /// Like [`ChatCounter::count_message`], but counts the images, audio clips and files with
+    /// the given estimator.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of the estimator.
+    pub fn count_message_with_estimator<E>(
+        &self,
+        message: &ChatCompletionRequestMessage,
+        estimator: &E,
+    ) -> Result<MessageTokens>
+    where
+        E: ContentPartEstimator + ?Sized,
+    {
+        let (bpe, model) = (self.bpe, self.model.as_str());
+        let mut tokens = MessageTokens {
+            role: bpe.count_with_special_tokens(&message.role),
+            framing: self.tokens_per_message,
+            ..Default::default()
+        };
         if let Some(content) = &message.content {
-            num_tokens += bpe.count_with_special_tokens(content) as i32;
+            tokens.content = bpe.count_with_special_tokens(&content.text());
+            if let Some(refusal) = content.refusal() {
+                tokens.refusal = bpe.count_with_special_tokens(&refusal);
+            }
         }
         if let Some(name) = &message.name {
-            num_tokens += bpe.count_with_special_tokens(name) as i32;
-            num_tokens += tokens_per_name;
+            tokens.name = bpe.count_with_special_tokens(name);
+            tokens.framing = tokens.framing.saturating_add_signed(self.tokens_per_name);
         }
-        if let Some(function_call) = &message.function_call {
-            num_tokens += bpe.count_with_special_tokens(&function_call.name) as i32;
-            num_tokens += bpe.count_with_special_tokens(&function_call.arguments) as i32;
-            num_tokens += FUNCTION_CALL_OVERHEAD;
-        }
-        for tool_call in &message.tool_calls {
-            num_tokens += bpe.count_with_special_tokens(&tool_call.name) as i32;
-            num_tokens += bpe.count_with_special_tokens(&tool_call.arguments) as i32;
-            num_tokens += FUNCTION_CALL_OVERHEAD;
+        let calls = message
+            .function_call
+            .iter()
+            .map(|call| (call.name.as_str(), call.arguments.as_str()))
+            .chain(
+                message
+                    .tool_calls
+                    .iter()
+                    .map(|call| (call.name(), call.arguments())),
+            );
+        for (name, arguments) in calls {
+            tokens.tool_calls += bpe.count_with_special_tokens(name);
+            tokens.tool_calls += bpe.count_with_special_tokens(arguments);
+            tokens.framing += FUNCTION_CALL_OVERHEAD;
         }
         if let Some(refusal) = &message.refusal {
-            num_tokens += bpe.count_with_special_tokens(refusal) as i32;
+            tokens.refusal += bpe.count_with_special_tokens(refusal);
+        }
+        for part in message.content.iter().flat_map(MessageContent::parts) {
+            match part {
+                ContentPart::Text(_) | ContentPart::Refusal(_) => {}
+                ContentPart::Image(image) => {
+                    tokens.media += estimator.image_tokens(model, image)?
+                }
+                ContentPart::Audio(audio) => {
+                    tokens.media += estimator.audio_tokens(model, audio)?
+                }
+                ContentPart::File(file) => {
+                    let text_tokens = bpe.count_with_special_tokens(&file.text);
+                    tokens.media += estimator.file_tokens(model, file, text_tokens)?
+                }
+            }
         }
+        Ok(tokens)
+    }
+
+    /// Returns the number of tokens left for the completion, see
+    /// [`get_chat_completion_max_tokens`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the context size of the model is unknown, or if a content part
+    /// cannot be counted for the model.
+    pub fn max_tokens(&self, messages: &[ChatCompletionRequestMessage]) -> Result<usize> {
+        let context_size = get_context_size(&self.model)
+            .ok_or_else(|| anyhow!("Unknown context size for model {}", self.model))?;
+        Ok(context_size.saturating_sub(self.num_tokens(messages)?))
     }
-    num_tokens += REPLY_PRIMING;
-    Ok(num_tokens as usize)
 }
 
 /// Calculates the maximum number of tokens available for chat completion based on the model and messages provided.
@@ -195,17 +767,17 @@ pub fn num_tokens_from_messages(
 /// let model = "gpt-3.5-turbo";
 /// let messages = vec![
 ///     ChatCompletionRequestMessage {
-///         content: Some("You are a helpful assistant that only speaks French.".to_string()),
+///         content: Some("You are a helpful assistant that only speaks French.".into()),
 ///         role: "system".to_string(),
 ///         ..Default::default()
 ///     },
 ///     ChatCompletionRequestMessage {
-///         content: Some("Hello, how are you?".to_string()),
+///         content: Some("Hello, how are you?".into()),
 ///         role: "user".to_string(),
 ///         ..Default::default()
 ///     },
 ///     ChatCompletionRequestMessage {
-///         content: Some("Parlez-vous francais?".to_string()),
+///         content: Some("Parlez-vous francais?".into()),
 ///         role: "system".to_string(),
 ///         ..Default::default()
 ///     },
@@ -221,88 +793,645 @@ pub fn get_chat_completion_max_tokens(
     model: &str,
     messages: &[ChatCompletionRequestMessage],
 ) -> Result<usize> {
-    let context_size = get_context_size(model)
-        .ok_or_else(|| anyhow!("Unknown context size for model {}", model))?;
-    let prompt_tokens = num_tokens_from_messages(model, messages)?;
-    Ok(context_size.saturating_sub(prompt_tokens))
-}
-
-fn bpe_singleton(tokenizer: Tokenizer) -> &'static CoreBPE {
-    match tokenizer {
-        Tokenizer::O200kHarmony => o200k_harmony_singleton(),
-        Tokenizer::O200kBase => o200k_base_singleton(),
-        Tokenizer::Cl100kBase => cl100k_base_singleton(),
-        Tokenizer::R50kBase => r50k_base_singleton(),
-        Tokenizer::P50kBase => p50k_base_singleton(),
-        Tokenizer::P50kEdit => p50k_edit_singleton(),
-        Tokenizer::Gpt2 => r50k_base_singleton(),
-    }
+    ChatCounter::new(model)?.max_tokens(messages)
 }
 
-/// Returns a cached reference to the BPE tokenizer for the given model name.
+/// Estimates the number of prompt tokens of chat messages for a model of another provider.
 ///
-/// Looks up which tokenizer the model uses, then returns a `&'static CoreBPE` singleton.
-/// The singleton is initialized once and reused for all subsequent calls.
+/// The messages are counted with the encoding of the model's [`EstimationProfile`], as
+/// a [`ChatCounter`] with that encoding does, and the count is scaled by the profile's
+/// correction factor. The result is an estimate, not an exact count.
 ///
 /// # Arguments
 ///
-/// * `model` - A model name, e.g., `"gpt-4o"`, `"gpt-3.5-turbo"`, `"o3-mini"`.
+/// * `model` - A string slice representing the model name, e.g., `"claude-sonnet-4-5"`.
+/// * `messages` - A slice of `ChatCompletionRequestMessage` instances.
 ///
 /// # Errors
 ///
-/// Returns an error if no tokenizer is found for the given model name.
+/// Returns an error if the model has no estimation profile (see [`get_estimation_profile`]),
+/// or if the messages cannot be counted.
 ///
 /// # Examples
 ///
 /// ```
-/// use tiktoken_rs::bpe_for_model;
+/// use tiktoken_rs::{estimate_num_tokens_from_messages, ChatCompletionRequestMessage};
 ///
-/// let bpe = bpe_for_model("gpt-4o").unwrap();
-/// let tokens = bpe.encode_with_special_tokens("hello world");
+/// let messages = vec![ChatCompletionRequestMessage {
+///     role: "user".to_string(),
+///     content: Some("Hello, world!".into()),
+///     ..Default::default()
+/// }];
+/// // 11 cl100k_base tokens, scaled by 1.2
+/// let num_tokens = estimate_num_tokens_from_messages("claude-sonnet-4-5", &messages).unwrap();
+/// assert_eq!(num_tokens, 14);
 /// ```
-pub fn bpe_for_model(model: &str) -> Result<&'static CoreBPE> {
-    let tokenizer =
-        get_tokenizer(model).ok_or_else(|| anyhow!("No tokenizer found for model {}", model))?;
-    bpe_for_tokenizer(tokenizer)
+pub fn estimate_num_tokens_from_messages(
+    model: &str,
+    messages: &[ChatCompletionRequestMessage],
+) -> Result<usize> {
+    let profile = estimation_profile(model)?;
+    let counter = ChatCounter::with_counter(model, bpe_singleton(profile.tokenizer))?;
+    Ok(profile.estimate(counter.num_tokens(messages)?))
 }
 
-/// Use [`bpe_for_model`] instead.
-#[deprecated(since = "0.10.0", note = "renamed to `bpe_for_model`")]
-pub fn get_bpe_from_model(model: &str) -> Result<&'static CoreBPE> {
-    bpe_for_model(model)
+/// Estimates the number of tokens left for the completion of a chat request to a model of
+/// another provider.
+///
+/// Returns the context size of the model's [`EstimationProfile`], minus the estimate of
+/// [`estimate_num_tokens_from_messages`], capped at the model's maximum output size.
+///
+/// # Arguments
+///
+/// * `model` - A string slice representing the model name, e.g., `"gemini-2.5-flash"`.
+/// * `messages` - A slice of `ChatCompletionRequestMessage` instances.
+///
+/// # Errors
+///
+/// Returns the same errors as [`estimate_num_tokens_from_messages`].
+pub fn estimate_chat_completion_max_tokens(
+    model: &str,
+    messages: &[ChatCompletionRequestMessage],
+) -> Result<usize> {
+    let profile = estimation_profile(model)?;
+    let prompt_tokens = estimate_num_tokens_from_messages(model, messages)?;
+    let max_tokens = profile.context_size.saturating_sub(prompt_tokens);
+    Ok(profile
+        .max_output_tokens
+        .map_or(max_tokens, |max_output_tokens| {
+            max_tokens.min(max_output_tokens)
+        }))
 }
 
-/// Returns a cached reference to the BPE tokenizer for the given tokenizer type.
+fn estimation_profile(model: &str) -> Result<EstimationProfile> {
+    get_estimation_profile(model)
+        .ok_or_else(|| anyhow!("No estimation profile found for model {}", model))
+}
+
+/// Controls which (if any) tool is called by the model, as given in the `tool_choice`
+/// (or legacy `function_call`) field of a request.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum ToolChoice {
+    /// The model picks between generating a message or calling tools.
+    #[default]
+    Auto,
+    /// The model does not call any tool.
+    None,
+    /// The model must call one or more tools.
+    Required,
+    /// The model must call the function with this name.
+    Function(String),
+}
+
+/// The parts of a chat completion request that consume prompt tokens.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ChatCompletionRequest {
+    /// The messages of the conversation so far.
+    pub messages: Vec<ChatCompletionRequestMessage>,
+    /// The functions the model may call. Requires the `tools` feature.
+    #[cfg(feature = "tools")]
+    pub tools: Vec<FunctionDefinition>,
+    /// Which tool the model should call, if any. `None` means the field is omitted.
+    pub tool_choice: Option<ToolChoice>,
+    /// The format the model must output. `None` means the field is omitted. Requires the
+    /// `tools` feature.
+    #[cfg(feature = "tools")]
+    pub response_format: Option<ResponseFormat>,
+}
+
+/// Returns the number of prompt tokens of a whole chat completion request.
 ///
-/// Returns a `&'static CoreBPE` singleton. The singleton is initialized once and reused
-/// for all subsequent calls.
+/// In addition to the messages counted by [`num_tokens_from_messages`], this counts the tool
+/// definitions (see [`num_tokens_from_functions`](crate::tools::num_tokens_from_functions)),
+/// the `tool_choice` setting and the response format (see
+/// [`num_tokens_from_response_format`](crate::tools::num_tokens_from_response_format), whose
+/// count is an approximation), which are invisible to a message-only count. Tool definitions
+/// and response formats are only available with the `tools` feature.
 ///
 /// # Arguments
 ///
-/// * `tokenizer` - A [`Tokenizer`] enum variant.
+/// * `model` - A string slice representing the model name, e.g., `"gpt-4o"`.
+/// * `request` - The request to count.
+///
+/// # Errors
+///
+/// Returns an error if the messages cannot be counted for the model
+/// (see [`num_tokens_from_messages`]).
 ///
 /// # Examples
 ///
 /// ```
-/// use tiktoken_rs::bpe_for_tokenizer;
-/// use tiktoken_rs::tokenizer::Tokenizer;
+/// # #[cfg(feature = "tools")] {
+/// use serde_json::json;
+/// use tiktoken_rs::tools::FunctionDefinition;
+/// use tiktoken_rs::{num_tokens_from_request, ChatCompletionRequest, ChatCompletionRequestMessage};
 ///
-/// let bpe = bpe_for_tokenizer(Tokenizer::O200kBase).unwrap();
-/// let tokens = bpe.encode_with_special_tokens("hello world");
+/// let request = ChatCompletionRequest {
+///     messages: vec![ChatCompletionRequestMessage {
+///         role: "user".to_string(),
+///         content: Some("What's the weather in Paris?".into()),
+///         ..Default::default()
+///     }],
+///     tools: vec![FunctionDefinition {
+///         name: "get_weather".to_string(),
+///         description: Some("Get the current weather for a city".to_string()),
+///         parameters: Some(json!({
+///             "type": "object",
+///             "properties": { "city": { "type": "string" } },
+///             "required": ["city"],
+///         })),
+///     }],
+///     ..Default::default()
+/// };
+/// let num_tokens = num_tokens_from_request("gpt-4o", &request).unwrap();
+/// # }
 /// ```
-pub fn bpe_for_tokenizer(tokenizer: Tokenizer) -> Result<&'static CoreBPE> {
-    Ok(bpe_singleton(tokenizer))
+pub fn num_tokens_from_request(model: &str, request: &ChatCompletionRequest) -> Result<usize> {
+    ChatCounter::new(model)?.num_tokens_from_request(request)
 }
 
-/// Use [`bpe_for_tokenizer`] instead.
-#[deprecated(since = "0.10.0", note = "renamed to `bpe_for_tokenizer`")]
-pub fn get_bpe_from_tokenizer(tokenizer: Tokenizer) -> Result<&'static CoreBPE> {
+impl<C: TokenCounter + ?Sized> ChatCounter<'_, C> {
+    /// Returns the number of prompt tokens of a whole chat completion request, see
+    /// [`num_tokens_from_request`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a content part cannot be counted for the model.
+    pub fn num_tokens_from_request(&self, request: &ChatCompletionRequest) -> Result<usize> {
+        // When tools are present, they are rendered into the system prompt. The first system
+        // message is separated from them by a newline, and the framing of the tools section
+        // shares 4 tokens with the system message.
+        #[cfg(feature = "tools")]
+        const SYSTEM_MESSAGE_DISCOUNT: usize = 4;
+
+        let system_index = request
+            .messages
+            .iter()
+            .position(|m| m.role == "system" || m.role == "developer");
+        let count_messages = |messages| self.num_tokens(messages);
+        #[cfg(feature = "tools")]
+        let has_tools = !request.tools.is_empty();
+        #[cfg(not(feature = "tools"))]
+        let has_tools = false;
+        let mut num_tokens = if !has_tools || system_index.is_none() {
+            count_messages(&request.messages)?
+        } else {
+            let mut messages = request.messages.clone();
+            if let Some(content) = system_index.and_then(|i| messages[i].content.as_mut()) {
+                content.push_str("\n");
+            }
+            count_messages(&messages)?
+        };
+
+        let bpe = self.bpe;
+        #[cfg(feature = "tools")]
+        if has_tools {
+            num_tokens += count_function_definitions(bpe, &request.tools);
+            if system_index.is_some() {
+                num_tokens = num_tokens.saturating_sub(SYSTEM_MESSAGE_DISCOUNT);
+            }
+        }
+        match &request.tool_choice {
+            Some(ToolChoice::None) => num_tokens += 1,
+            Some(ToolChoice::Function(name)) => {
+                num_tokens += bpe.count_with_special_tokens(name) + 4
+            }
+            Some(ToolChoice::Auto | ToolChoice::Required) | None => {}
+        }
+        #[cfg(feature = "tools")]
+        if let Some(format) = &request.response_format {
+            num_tokens += count_response_format(bpe, format);
+        }
+        Ok(num_tokens)
+    }
+}
+
+/// The `reasoning_effort` setting of a request to a reasoning model.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReasoningEffort {
+    /// No reasoning (supported by some GPT-5 series models).
+    None,
+    /// Very little reasoning, for the fastest responses.
+    Minimal,
+    /// Less reasoning than the default.
+    Low,
+    /// The API default.
+    #[default]
+    Medium,
+    /// More reasoning than the default.
+    High,
+    /// The most reasoning (supported by some GPT-5 series models).
+    XHigh,
+}
+
+impl ReasoningEffort {
+    /// Returns a rough estimate of the number of hidden reasoning tokens spent at this effort.
+    ///
+    /// The actual number varies a lot with the task; these values are meant as a reservation
+    /// that leaves room for typical reasoning, not as a prediction.
+    pub fn estimated_reasoning_tokens(self) -> usize {
+        match self {
+            ReasoningEffort::None => 0,
+            ReasoningEffort::Minimal => 1_024,
+            ReasoningEffort::Low => 4_096,
+            ReasoningEffort::Medium => 16_384,
+            ReasoningEffort::High => 32_768,
+            ReasoningEffort::XHigh => 65_536,
+        }
+    }
+}
+
+/// The output budget of a chat completion request, as returned by
+/// [`get_chat_completion_output_budget`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OutputBudget {
+    /// Number of tokens in the prompt.
+    pub prompt_tokens: usize,
+    /// Maximum number of tokens the model can generate for this request, reasoning included.
+    /// This is the value to use for `max_completion_tokens`.
+    pub max_completion_tokens: usize,
+    /// Estimated number of tokens reserved for hidden reasoning. Always 0 for models that do not reason.
+    pub reasoning_tokens: usize,
+    /// Number of tokens left for the visible output once reasoning is accounted for.
+    pub visible_output_tokens: usize,
+}
+
+/// Calculates the output budget of a chat completion request, accounting for reasoning tokens.
+///
+/// Unlike [`get_chat_completion_max_tokens`], which returns `context_size - prompt_tokens`, this
+/// also caps the completion at the model's maximum output size (see [`get_max_output_tokens`]) and,
+/// for reasoning models, reserves an estimate of the hidden reasoning tokens (see
+/// [`ReasoningEffort::estimated_reasoning_tokens`]) out of that budget.
+///
+/// # Arguments
+///
+/// * `model` - A string slice representing the model name, e.g., `"o3-mini"`.
+/// * `messages` - A slice of `ChatCompletionRequestMessage` instances containing the chat context.
+/// * `reasoning_effort` - The `reasoning_effort` of the request, or `None` for the API default (medium).
+///
+/// # Errors
+///
+/// Returns an error if the context size is unknown or the messages cannot be counted for the model.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::{get_chat_completion_output_budget, ChatCompletionRequestMessage, ReasoningEffort};
+///
+/// let messages = vec![ChatCompletionRequestMessage {
+///     role: "user".to_string(),
+///     content: Some("Prove that there are infinitely many primes.".into()),
+///     ..Default::default()
+/// }];
+/// let budget =
+///     get_chat_completion_output_budget("o3-mini", &messages, Some(ReasoningEffort::High)).unwrap();
+/// assert_eq!(budget.max_completion_tokens, 100_000);
+/// assert_eq!(budget.reasoning_tokens + budget.visible_output_tokens, 100_000);
+/// ```
+pub fn get_chat_completion_output_budget(
+    model: &str,
+    messages: &[ChatCompletionRequestMessage],
+    reasoning_effort: Option<ReasoningEffort>,
+) -> Result<OutputBudget> {
+    ChatCounter::new(model)?.output_budget(messages, reasoning_effort)
+}
+
+impl<C: TokenCounter + ?Sized> ChatCounter<'_, C> {
+    /// Returns the output budget of a chat completion request, see
+    /// [`get_chat_completion_output_budget`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the context size of the model is unknown, or if a content part
+    /// cannot be counted for the model.
+    pub fn output_budget(
+        &self,
+        messages: &[ChatCompletionRequestMessage],
+        reasoning_effort: Option<ReasoningEffort>,
+    ) -> Result<OutputBudget> {
+        let model = self.model.as_str();
+        let context_size = get_context_size(model)
+            .ok_or_else(|| anyhow!("Unknown context size for model {}", model))?;
+        let prompt_tokens = self.num_tokens(messages)?;
+        let mut max_completion_tokens = context_size.saturating_sub(prompt_tokens);
+        if let Some(max_output_tokens) = get_max_output_tokens(model) {
+            max_completion_tokens = max_completion_tokens.min(max_output_tokens);
+        }
+        let reasoning_tokens = if get_model_capabilities(model).is_some_and(|c| c.reasoning) {
+            reasoning_effort
+                .unwrap_or_default()
+                .estimated_reasoning_tokens()
+                .min(max_completion_tokens)
+        } else {
+            0
+        };
+        Ok(OutputBudget {
+            prompt_tokens,
+            max_completion_tokens,
+            reasoning_tokens,
+            visible_output_tokens: max_completion_tokens - reasoning_tokens,
+        })
+    }
+}
+
+/// Estimates the cost of a chat completion request, given a model and messages.
+///
+/// Prompt tokens are counted with [`num_tokens_from_messages`]. If `max_output_tokens` is `None`,
+/// the output is assumed to be as long as the model can generate: the model's maximum output
+/// size from [`get_max_output_tokens`], or the whole remaining context window (see
+/// [`get_chat_completion_max_tokens`]) if that is unknown or smaller. This gives an upper bound
+/// on the cost. An explicit `max_output_tokens` is capped to the remaining context window.
+///
+/// # Arguments
+///
+/// * `model` - A string slice representing the model name, e.g., `"gpt-4o"`.
+/// * `messages` - A slice of `ChatCompletionRequestMessage` instances containing the chat context.
+/// * `max_output_tokens` - The expected number of output tokens, or `None` for the worst case.
+/// * `pricing` - Prices to use instead of the built-in table from [`get_model_pricing`].
+///
+/// # Errors
+///
+/// Returns an error if the tokens cannot be counted for the model, if the context size is
+/// unknown, or if no pricing is given and the model has no built-in pricing.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::{estimate_chat_completion_cost, ChatCompletionRequestMessage};
+///
+/// let messages = vec![ChatCompletionRequestMessage {
+///     role: "user".to_string(),
+///     content: Some("Hello, how are you?".into()),
+///     ..Default::default()
+/// }];
+/// let cost = estimate_chat_completion_cost("gpt-4o", &messages, Some(500), None).unwrap();
+/// println!("Estimated cost: ${:.6}", cost.total_cost());
+/// ```
+pub fn estimate_chat_completion_cost(
+    model: &str,
+    messages: &[ChatCompletionRequestMessage],
+    max_output_tokens: Option<usize>,
+    pricing: Option<ModelPricing>,
+) -> Result<CostEstimate> {
+    let pricing = pricing_for_model(model, pricing)?;
+    let context_size = get_context_size(model)
+        .ok_or_else(|| anyhow!("Unknown context size for model {}", model))?;
+    let prompt_tokens = num_tokens_from_messages(model, messages)?;
+    let output_tokens =
+        output_tokens_for_cost(model, context_size, prompt_tokens, max_output_tokens);
+    Ok(pricing.estimate(prompt_tokens, 0, output_tokens))
+}
+
+/// Estimates the cost of a legacy text completion request, given a model and prompt.
+///
+/// The remaining budget is computed as in [`get_text_completion_max_tokens`]. If `max_output_tokens`
+/// is `None`, the output is assumed to be as long as the model can generate: the model's maximum
+/// output size from [`get_max_output_tokens`], or the whole remaining budget if that is unknown
+/// or smaller. This gives an upper bound on the cost. An explicit `max_output_tokens` is capped
+/// to the remaining context window.
+///
+/// # Arguments
+///
+/// * `model` - A string slice representing the model name, e.g., `"davinci-002"`.
+/// * `prompt` - A string slice containing the prompt text.
+/// * `max_output_tokens` - The expected number of output tokens, or `None` for the worst case.
+/// * `pricing` - Prices to use instead of the built-in table from [`get_model_pricing`].
+///
+/// # Errors
+///
+/// Returns an error if no tokenizer or context size is found for the model, or if no pricing
+/// is given and the model has no built-in pricing.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::estimate_text_completion_cost;
+/// use tiktoken_rs::model::ModelPricing;
+///
+/// let pricing = ModelPricing::new(2.00, None, 2.00);
+/// let cost =
+///     estimate_text_completion_cost("davinci-002", "Once upon a time", Some(100), Some(pricing))
+///         .unwrap();
+/// assert_eq!(cost.output_tokens, 100);
+/// ```
+pub fn estimate_text_completion_cost(
+    model: &str,
+    prompt: &str,
+    max_output_tokens: Option<usize>,
+    pricing: Option<ModelPricing>,
+) -> Result<CostEstimate> {
+    let pricing = pricing_for_model(model, pricing)?;
+    let context_size = get_context_size(model)
+        .ok_or_else(|| anyhow!("Unknown context size for model {}", model))?;
+    let prompt_tokens = bpe_for_model(model)?.count_with_special_tokens(prompt);
+    let output_tokens =
+        output_tokens_for_cost(model, context_size, prompt_tokens, max_output_tokens);
+    Ok(pricing.estimate(prompt_tokens, 0, output_tokens))
+}
+
+/// The number of output tokens to price: `max_output_tokens` if given, otherwise the most the
+/// model can generate. Either way capped to the remaining context window.
+fn output_tokens_for_cost(
+    model: &str,
+    context_size: usize,
+    prompt_tokens: usize,
+    max_output_tokens: Option<usize>,
+) -> usize {
+    let available = context_size.saturating_sub(prompt_tokens);
+    max_output_tokens
+        .or_else(|| get_max_output_tokens(model))
+        .map_or(available, |n| n.min(available))
+}
+
+fn pricing_for_model(model: &str, pricing: Option<ModelPricing>) -> Result<ModelPricing> {
+    pricing
+        .or_else(|| get_model_pricing(model))
+        .ok_or_else(|| anyhow!("No pricing found for model {}", model))
+}
+
+/// A group of embedding inputs that fits in a single embeddings request.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EmbeddingBatch {
+    /// Indices of the inputs in this batch, in their original order.
+    pub indices: Vec<usize>,
+    /// Total number of tokens of the inputs in this batch.
+    pub num_tokens: usize,
+}
+
+/// An embedding input that is longer than the model's per-input token limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OversizedEmbeddingInput {
+    /// Index of the input.
+    pub index: usize,
+    /// Number of tokens in the input.
+    pub num_tokens: usize,
+}
+
+/// The result of [`batch_embedding_inputs`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EmbeddingBatches {
+    /// Batches of inputs, each within the per-request input count and token limits.
+    pub batches: Vec<EmbeddingBatch>,
+    /// Inputs that exceed the per-input token limit. These are not part of any batch and must
+    /// be shortened or split before they can be embedded.
+    pub oversized: Vec<OversizedEmbeddingInput>,
+}
+
+/// Validates embedding inputs against a model's limits and partitions them into requests.
+///
+/// Each input is checked against the per-input token limit, and the remaining inputs are
+/// grouped, in order, into batches that respect both the per-request input count and the
+/// per-request total token limit from [`get_embedding_limits`].
+///
+/// # Arguments
+///
+/// * `model` - A string slice representing the embedding model name, e.g., `"text-embedding-3-small"`.
+/// * `inputs` - The texts to embed.
+///
+/// # Errors
+///
+/// Returns an error if the model is not a known embedding model or has no tokenizer.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::batch_embedding_inputs;
+///
+/// let inputs = ["first document", "second document"];
+/// let plan = batch_embedding_inputs("text-embedding-3-small", &inputs).unwrap();
+/// assert_eq!(plan.batches.len(), 1);
+/// assert_eq!(plan.batches[0].indices, vec![0, 1]);
+/// assert!(plan.oversized.is_empty());
+/// ```
+pub fn batch_embedding_inputs<S: AsRef<str>>(
+    model: &str,
+    inputs: &[S],
+) -> Result<EmbeddingBatches> {
+    let limits = get_embedding_limits(model)
+        .ok_or_else(|| anyhow!("Unknown embedding limits for model {}", model))?;
+    let bpe = bpe_for_model(model)?;
+    let token_counts = inputs
+        .iter()
+        .map(|input| bpe.count_with_special_tokens(input.as_ref()));
+    Ok(plan_embedding_batches(limits, token_counts))
+}
+
+fn plan_embedding_batches(
+    limits: EmbeddingLimits,
+    token_counts: impl IntoIterator<Item = usize>,
+) -> EmbeddingBatches {
+    let mut plan = EmbeddingBatches::default();
+    let mut current = EmbeddingBatch::default();
+    for (index, num_tokens) in token_counts.into_iter().enumerate() {
+        if let Some(input) = oversized_embedding_input(&limits, index, num_tokens) {
+            plan.oversized.push(input);
+            continue;
+        }
+        if current.indices.len() == limits.max_inputs_per_request
+            || current.num_tokens + num_tokens > limits.max_tokens_per_request
+        {
+            plan.batches.push(std::mem::take(&mut current));
+        }
+        current.indices.push(index);
+        current.num_tokens += num_tokens;
+    }
+    if !current.indices.is_empty() {
+        plan.batches.push(current);
+    }
+    plan
+}
+
+/// Returns the input at `index` if it is longer than the per-input token limit.
+fn oversized_embedding_input(
+    limits: &EmbeddingLimits,
+    index: usize,
+    num_tokens: usize,
+) -> Option<OversizedEmbeddingInput> {
+    (num_tokens > limits.max_input_tokens).then_some(OversizedEmbeddingInput { index, num_tokens })
+}
+
+/// Infallible version of [`bpe_for_tokenizer`]: every tokenizer has a built-in encoding.
+pub(crate) fn bpe_singleton(tokenizer: Tokenizer) -> &'static CoreBPE {
+    match tokenizer {
+        Tokenizer::O200kHarmony => o200k_harmony_singleton(),
+        Tokenizer::O200kBase => o200k_base_singleton(),
+        Tokenizer::Cl100kBase => cl100k_base_singleton(),
+        Tokenizer::R50kBase => r50k_base_singleton(),
+        Tokenizer::P50kBase => p50k_base_singleton(),
+        Tokenizer::P50kEdit => p50k_edit_singleton(),
+        Tokenizer::Gpt2 => r50k_base_singleton(),
+    }
+}
+
+/// Returns a cached reference to the BPE tokenizer for the given model name.
+///
+/// Looks up which tokenizer the model uses, then returns a `&'static CoreBPE` singleton.
+/// The singleton is initialized once and reused for all subsequent calls.
+///
+/// If the model is deprecated, the hook registered with
+/// [`set_deprecation_hook`](crate::model::set_deprecation_hook) is invoked.
+///
+/// # Arguments
+///
+/// * `model` - A model name, e.g., `"gpt-4o"`, `"gpt-3.5-turbo"`, `"o3-mini"`.
+///
+/// # Errors
+///
+/// Returns an error if no tokenizer is found for the given model name.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::bpe_for_model;
+///
+/// let bpe = bpe_for_model("gpt-4o").unwrap();
+/// let tokens = bpe.encode_with_special_tokens("hello world");
+/// ```
+pub fn bpe_for_model(model: &str) -> Result<&'static CoreBPE> {
+    let tokenizer =
+        get_tokenizer(model).ok_or_else(|| anyhow!("No tokenizer found for model {}", model))?;
+    notify_if_deprecated(model);
+    bpe_for_tokenizer(tokenizer)
+}
+
+/// Use [`bpe_for_model`] instead.
+#[deprecated(since = "0.10.0", note = "renamed to `bpe_for_model`")]
+pub fn get_bpe_from_model(model: &str) -> Result<&'static CoreBPE> {
+    bpe_for_model(model)
+}
+
+/// Returns a cached reference to the BPE tokenizer for the given tokenizer type.
+///
+/// Returns a `&'static CoreBPE` singleton. The singleton is initialized once and reused
+/// for all subsequent calls.
+///
+/// # Arguments
+///
+/// * `tokenizer` - A [`Tokenizer`] enum variant.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::bpe_for_tokenizer;
+/// use tiktoken_rs::tokenizer::Tokenizer;
+///
+/// let bpe = bpe_for_tokenizer(Tokenizer::O200kBase).unwrap();
+/// let tokens = bpe.encode_with_special_tokens("hello world");
+/// ```
+pub fn bpe_for_tokenizer(tokenizer: Tokenizer) -> Result<&'static CoreBPE> {
+    Ok(bpe_singleton(tokenizer))
+}
+
+/// Use [`bpe_for_tokenizer`] instead.
+#[deprecated(since = "0.10.0", note = "renamed to `bpe_for_tokenizer`")]
+pub fn get_bpe_from_tokenizer(tokenizer: Tokenizer) -> Result<&'static CoreBPE> {
     bpe_for_tokenizer(tokenizer)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Rank;
 
     #[test]
     fn test_bpe_for_tokenizer() {
@@ -316,37 +1445,37 @@ mod tests {
             ChatCompletionRequestMessage {
                 role: "system".to_string(),
                 name: None,
-                content: Some("You are a helpful, pattern-following assistant that translates corporate jargon into plain English.".to_string()),
+                content: Some("You are a helpful, pattern-following assistant that translates corporate jargon into plain English.".into()),
                 ..Default::default()
             },
             ChatCompletionRequestMessage {
                 role: "system".to_string(),
                 name: Some("example_user".to_string()),
-                content: Some("New synergies will help drive top-line growth.".to_string()),
+                content: Some("New synergies will help drive top-line growth.".into()),
                 ..Default::default()
             },
             ChatCompletionRequestMessage {
                 role: "system".to_string(),
                 name: Some("example_assistant".to_string()),
-                content: Some("Things working well together will increase revenue.".to_string()),
+                content: Some("Things working well together will increase revenue.".into()),
                 ..Default::default()
             },
             ChatCompletionRequestMessage {
                 role: "system".to_string(),
                 name: Some("example_user".to_string()),
-                content: Some("Let's circle back when we have more bandwidth to touch base on opportunities for increased leverage.".to_string()),
+                content: Some("Let's circle back when we have more bandwidth to touch base on opportunities for increased leverage.".into()),
                 ..Default::default()
             },
             ChatCompletionRequestMessage {
                 role: "system".to_string(),
                 name: Some("example_assistant".to_string()),
-                content: Some("Let's talk later when we're less busy about how to do better.".to_string()),
+                content: Some("Let's talk later when we're less busy about how to do better.".into()),
                 ..Default::default()
             },
             ChatCompletionRequestMessage {
                 role: "user".to_string(),
                 name: None,
-                content: Some("This late pivot means we don't have time to boil the ocean for the client deliverable.".to_string()),
+                content: Some("This late pivot means we don't have time to boil the ocean for the client deliverable.".into()),
                 ..Default::default()
             },
         ];
@@ -369,25 +1498,25 @@ mod tests {
         let messages = vec![
             ChatCompletionRequestMessage {
                 role: "system".to_string(),
-                content: Some("You are a friendly chatbot.\n".to_string()),
+                content: Some("You are a friendly chatbot.\n".into()),
                 name: None,
                 ..Default::default()
             },
             ChatCompletionRequestMessage {
                 role: "assistant".to_string(),
-                content: Some("Hello, I am a friendly chatbot!\n".to_string()),
+                content: Some("Hello, I am a friendly chatbot!\n".into()),
                 name: None,
                 ..Default::default()
             },
             ChatCompletionRequestMessage {
                 role: "user".to_string(),
-                content: Some("What is the weather in New York?".to_string()),
+                content: Some("What is the weather in New York?".into()),
                 name: None,
                 ..Default::default()
             },
             ChatCompletionRequestMessage {
                 role: "assistant".to_string(),
-                content: Some(String::new()),
+                content: Some(String::new().into()),
                 function_call: Some(FunctionCall {
                     name: "get_weather".to_string(),
                     arguments: "{\n  \"city\": \"New York\"\n}".to_string(),
@@ -396,9 +1525,7 @@ mod tests {
             },
             ChatCompletionRequestMessage {
                 role: "function".to_string(),
-                content: Some(
-                    "{\"temperature\": 72, \"conditions\": \"partly_cloudy\"}".to_string(),
-                ),
+                content: Some("{\"temperature\": 72, \"conditions\": \"partly_cloudy\"}".into()),
                 name: Some("get_weather".to_string()),
                 ..Default::default()
             },
@@ -412,10 +1539,11 @@ mod tests {
     fn test_num_tokens_from_messages_with_tool_calls() {
         let messages_with = vec![ChatCompletionRequestMessage {
             role: "assistant".to_string(),
-            tool_calls: vec![FunctionCall {
-                name: "get_weather".to_string(),
-                arguments: r#"{"city": "Paris"}"#.to_string(),
-            }],
+            tool_calls: vec![ToolCall::function(
+                "call_1",
+                "get_weather",
+                r#"{"city": "Paris"}"#,
+            )],
             ..Default::default()
         }];
         let messages_without = vec![ChatCompletionRequestMessage {
@@ -434,23 +1562,18 @@ mod tests {
     fn test_num_tokens_from_messages_with_multiple_tool_calls() {
         let single = vec![ChatCompletionRequestMessage {
             role: "assistant".to_string(),
-            tool_calls: vec![FunctionCall {
-                name: "get_weather".to_string(),
-                arguments: r#"{"city": "Paris"}"#.to_string(),
-            }],
+            tool_calls: vec![ToolCall::function(
+                "call_1",
+                "get_weather",
+                r#"{"city": "Paris"}"#,
+            )],
             ..Default::default()
         }];
         let double = vec![ChatCompletionRequestMessage {
             role: "assistant".to_string(),
             tool_calls: vec![
-                FunctionCall {
-                    name: "get_weather".to_string(),
-                    arguments: r#"{"city": "Paris"}"#.to_string(),
-                },
-                FunctionCall {
-                    name: "get_weather".to_string(),
-                    arguments: r#"{"city": "London"}"#.to_string(),
-                },
+                ToolCall::function("call_1", "get_weather", r#"{"city": "Paris"}"#),
+                ToolCall::function("call_2", "get_weather", r#"{"city": "London"}"#),
             ],
             ..Default::default()
         }];
@@ -462,6 +1585,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tool_call_ids_are_not_counted() {
+        let call = |id: &str| ChatCompletionRequestMessage {
+            role: "assistant".to_string(),
+            tool_calls: vec![ToolCall::function(
+                id,
+                "get_weather",
+                r#"{"city": "Paris"}"#,
+            )],
+            ..Default::default()
+        };
+        let result = |id: &str| ChatCompletionRequestMessage {
+            role: "tool".to_string(),
+            content: Some("22C".into()),
+            tool_call_id: Some(id.to_string()),
+            ..Default::default()
+        };
+        let short = vec![call("a"), result("a")];
+        let long = vec![
+            call("call_0123456789abcdefghijklmn"),
+            result("call_0123456789abcdefghijklmn"),
+        ];
+        assert_eq!(
+            num_tokens_from_messages("gpt-4o", &short).unwrap(),
+            num_tokens_from_messages("gpt-4o", &long).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_custom_tool_calls_count_like_function_calls() {
+        let message = |call| ChatCompletionRequestMessage {
+            role: "assistant".to_string(),
+            tool_calls: vec![call],
+            ..Default::default()
+        };
+        let function = message(ToolCall::function("call_1", "shell", "ls -la"));
+        let custom = message(ToolCall::custom("call_1", "shell", "ls -la"));
+        assert_eq!(
+            num_tokens_from_messages("gpt-4o", &[function]).unwrap(),
+            num_tokens_from_messages("gpt-4o", &[custom]).unwrap()
+        );
+    }
+
     #[test]
     fn test_num_tokens_from_messages_with_refusal() {
         let messages_with = vec![ChatCompletionRequestMessage {
@@ -482,57 +1648,244 @@ mod tests {
     }
 
     #[test]
-    fn test_num_tokens_from_messages_repeated_calls_consistent() {
+    fn test_num_tokens_from_messages_rejects_non_chat_models() {
         let messages = vec![ChatCompletionRequestMessage {
             role: "user".to_string(),
-            content: Some("Hello, world!".to_string()),
+            content: Some("Hello, world!".into()),
             ..Default::default()
         }];
-        let first = num_tokens_from_messages("gpt-4o", &messages).unwrap();
-        for _ in 0..5 {
-            let result = num_tokens_from_messages("gpt-4o", &messages).unwrap();
-            assert_eq!(first, result);
-        }
-    }
-
-    #[test]
-    fn test_text_completion_max_tokens_repeated_calls_consistent() {
-        let first = get_text_completion_max_tokens("gpt-4o", "Hello, world!").unwrap();
-        for _ in 0..5 {
-            let result = get_text_completion_max_tokens("gpt-4o", "Hello, world!").unwrap();
-            assert_eq!(first, result);
-        }
+        // cl100k_base models that are not chat models
+        assert!(num_tokens_from_messages("davinci-002", &messages).is_err());
+        assert!(num_tokens_from_messages("text-embedding-3-small", &messages).is_err());
+        // p50k_base completion model
+        assert!(num_tokens_from_messages("text-davinci-003", &messages).is_err());
+        assert!(num_tokens_from_messages("ft:gpt-4o-mini:org:name:id", &messages).is_ok());
     }
 
     #[test]
-    fn test_bpe_singleton_matches_fresh_bpe() {
-        let singleton = bpe_singleton(Tokenizer::Cl100kBase);
-        let fresh = bpe_for_tokenizer(Tokenizer::Cl100kBase).unwrap();
-        let text = "The quick brown fox jumps over the lazy dog";
-        assert_eq!(
-            singleton.encode_with_special_tokens(text),
-            fresh.encode_with_special_tokens(text),
-        );
+    fn test_num_tokens_from_messages_with_images() {
+        let text = ChatCompletionRequestMessage {
+            role: "user".to_string(),
+            content: Some("What is in this image?".into()),
+            ..Default::default()
+        };
+        let with_image = ChatCompletionRequestMessage {
+            content: Some(
+                vec![
+                    "What is in this image?".into(),
+                    ContentPart::Image(ImageInput {
+                        width: 2048,
+                        height: 4096,
+                        detail: crate::multimodal::ImageDetail::High,
+                    }),
+                ]
+                .into(),
+            ),
+            ..text.clone()
+        };
+        let text_tokens = num_tokens_from_messages("gpt-4o", &[text]).unwrap();
+        let image_tokens =
+            num_tokens_from_messages("gpt-4o", std::slice::from_ref(&with_image)).unwrap();
+        assert_eq!(image_tokens, text_tokens + 1105);
+        assert!(num_tokens_from_messages("gpt-3.5-turbo", &[with_image]).is_err());
     }
 
     #[test]
-    fn test_get_chat_completion_max_tokens() {
+    fn test_num_tokens_from_messages_with_content_parts() {
+        let joined = ChatCompletionRequestMessage {
+            role: "assistant".to_string(),
+            content: Some("Paris".into()),
+            refusal: Some("No.".to_string()),
+            ..Default::default()
+        };
+        let parts = ChatCompletionRequestMessage {
+            role: "assistant".to_string(),
+            content: Some(vec!["Paris".into(), ContentPart::Refusal("No.".to_string())].into()),
+            ..Default::default()
+        };
+        let breakdown = num_tokens_breakdown_from_messages("gpt-4o", &[joined, parts]).unwrap();
+        assert_eq!(breakdown.messages[0], breakdown.messages[1]);
+    }
+
+    #[test]
+    fn test_num_tokens_from_messages_with_estimator() {
+        struct Fixed;
+        impl ContentPartEstimator for Fixed {
+            fn image_tokens(&self, _model: &str, _image: &ImageInput) -> Result<usize> {
+                Ok(100)
+            }
+            fn audio_tokens(&self, _model: &str, _audio: &AudioInput) -> Result<usize> {
+                Ok(10)
+            }
+        }
+        let text = ChatCompletionRequestMessage {
+            role: "user".to_string(),
+            content: Some("Describe these.".into()),
+            ..Default::default()
+        };
+        let with_media = ChatCompletionRequestMessage {
+            content: Some(
+                vec![
+                    "Describe these.".into(),
+                    ImageInput::new(512, 512).into(),
+                    ImageInput::new(1024, 1024).into(),
+                    AudioInput::new(std::time::Duration::from_secs(60)).into(),
+                ]
+                .into(),
+            ),
+            ..text.clone()
+        };
+        // The estimator also counts inputs the built-in formulas reject for the model
+        let text_tokens = num_tokens_from_messages("gpt-3.5-turbo", &[text]).unwrap();
+        assert_eq!(
+            num_tokens_from_messages_with_estimator("gpt-3.5-turbo", &[with_media], &Fixed)
+                .unwrap(),
+            text_tokens + 210
+        );
+    }
+
+    #[test]
+    fn test_num_tokens_breakdown_from_messages() {
+        let messages = vec![
+            ChatCompletionRequestMessage {
+                role: "system".to_string(),
+                content: Some("You are a helpful assistant.".into()),
+                name: Some("setup".to_string()),
+                ..Default::default()
+            },
+            ChatCompletionRequestMessage {
+                role: "assistant".to_string(),
+                tool_calls: vec![
+                    ToolCall::function("call_1", "get_weather", r#"{"city":"Paris"}"#),
+                    ToolCall::function("call_2", "get_time", "{}"),
+                ],
+                refusal: Some("I can't do that.".to_string()),
+                ..Default::default()
+            },
+        ];
+        let bpe = bpe_for_model("gpt-4o").unwrap();
+        let breakdown = num_tokens_breakdown_from_messages("gpt-4o", &messages).unwrap();
+        assert_eq!(
+            breakdown.messages[0],
+            MessageTokens {
+                role: bpe.count_with_special_tokens("system"),
+                name: bpe.count_with_special_tokens("setup"),
+                content: bpe.count_with_special_tokens("You are a helpful assistant."),
+                framing: 3 + 1,
+                ..Default::default()
+            }
+        );
+        let assistant = breakdown.messages[1];
+        assert_eq!(assistant.content, 0);
+        assert_eq!(assistant.framing, 3 + 2);
+        assert_eq!(
+            assistant.refusal,
+            bpe.count_with_special_tokens("I can't do that.")
+        );
+        assert!(assistant.tool_calls > 0);
+        assert_eq!(
+            breakdown.total(),
+            num_tokens_from_messages("gpt-4o", &messages).unwrap()
+        );
+
+        // The legacy snapshot's negative name overhead is folded into the framing
+        let legacy = num_tokens_breakdown_from_messages("gpt-3.5-turbo-0301", &messages).unwrap();
+        assert_eq!(legacy.messages[0].framing, 4 - 1);
+        assert_eq!(
+            legacy.total(),
+            num_tokens_from_messages("gpt-3.5-turbo-0301", &messages).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_num_tokens_from_messages_with_audio_and_files() {
+        let text = ChatCompletionRequestMessage {
+            role: "user".to_string(),
+            content: Some("Summarize this.".into()),
+            ..Default::default()
+        };
+        let with_audio = ChatCompletionRequestMessage {
+            content: Some(
+                vec![
+                    "Summarize this.".into(),
+                    AudioInput::new(std::time::Duration::from_secs(12)).into(),
+                ]
+                .into(),
+            ),
+            ..text.clone()
+        };
+        let model = "gpt-4o-audio-preview";
+        let text_tokens = num_tokens_from_messages(model, std::slice::from_ref(&text)).unwrap();
+        assert_eq!(
+            num_tokens_from_messages(model, std::slice::from_ref(&with_audio)).unwrap(),
+            text_tokens + 120
+        );
+        assert!(num_tokens_from_messages("gpt-4o", &[with_audio]).is_err());
+
+        let file = FileInput::new("Revenue grew 12% year over year.", 3);
+        let with_file = ChatCompletionRequestMessage {
+            content: Some(vec!["Summarize this.".into(), file.clone().into()].into()),
+            ..text.clone()
+        };
+        assert_eq!(
+            num_tokens_from_messages("gpt-4o", &[with_file]).unwrap(),
+            num_tokens_from_messages("gpt-4o", &[text]).unwrap()
+                + crate::multimodal::num_tokens_from_file("gpt-4o", &file).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_num_tokens_from_messages_repeated_calls_consistent() {
+        let messages = vec![ChatCompletionRequestMessage {
+            role: "user".to_string(),
+            content: Some("Hello, world!".into()),
+            ..Default::default()
+        }];
+        let first = num_tokens_from_messages("gpt-4o", &messages).unwrap();
+        for _ in 0..5 {
+            let result = num_tokens_from_messages("gpt-4o", &messages).unwrap();
+            assert_eq!(first, result);
+        }
+    }
+
+    #[test]
+    fn test_text_completion_max_tokens_repeated_calls_consistent() {
+        let first = get_text_completion_max_tokens("gpt-4o", "Hello, world!").unwrap();
+        for _ in 0..5 {
+            let result = get_text_completion_max_tokens("gpt-4o", "Hello, world!").unwrap();
+            assert_eq!(first, result);
+        }
+    }
+
+    #[test]
+    fn test_bpe_singleton_matches_fresh_bpe() {
+        let singleton = bpe_singleton(Tokenizer::Cl100kBase);
+        let fresh = bpe_for_tokenizer(Tokenizer::Cl100kBase).unwrap();
+        let text = "The quick brown fox jumps over the lazy dog";
+        assert_eq!(
+            singleton.encode_with_special_tokens(text),
+            fresh.encode_with_special_tokens(text),
+        );
+    }
+
+    #[test]
+    fn test_get_chat_completion_max_tokens() {
         let model = "gpt-3.5-turbo";
         let messages = vec![
             ChatCompletionRequestMessage {
-                content: Some("You are a helpful assistant that only speaks French.".to_string()),
+                content: Some("You are a helpful assistant that only speaks French.".into()),
                 role: "system".to_string(),
                 name: None,
                 ..Default::default()
             },
             ChatCompletionRequestMessage {
-                content: Some("Hello, how are you?".to_string()),
+                content: Some("Hello, how are you?".into()),
                 role: "user".to_string(),
                 name: None,
                 ..Default::default()
             },
             ChatCompletionRequestMessage {
-                content: Some("Parlez-vous francais?".to_string()),
+                content: Some("Parlez-vous francais?".into()),
                 role: "system".to_string(),
                 name: None,
                 ..Default::default()
@@ -549,22 +1902,490 @@ mod tests {
         let max_tokens = get_text_completion_max_tokens(model, prompt).unwrap();
         assert!(max_tokens > 0);
     }
+
+    #[test]
+    #[cfg(feature = "tools")]
+    fn test_num_tokens_from_request_with_functions() {
+        // Expected values from https://github.com/hmarr/openai-chat-tokens, validated against
+        // the OpenAI API.
+        let user_message = ChatCompletionRequestMessage {
+            role: "user".to_string(),
+            content: Some("hello".into()),
+            ..Default::default()
+        };
+        let foo = FunctionDefinition {
+            name: "foo".to_string(),
+            parameters: Some(serde_json::json!({ "type": "object", "properties": {} })),
+            ..Default::default()
+        };
+        let request = ChatCompletionRequest {
+            messages: vec![user_message.clone()],
+            ..Default::default()
+        };
+        assert_eq!(
+            num_tokens_from_request("gpt-3.5-turbo", &request).unwrap(),
+            8
+        );
+
+        let request = ChatCompletionRequest {
+            messages: vec![user_message.clone()],
+            tools: vec![foo.clone()],
+            ..Default::default()
+        };
+        assert_eq!(
+            num_tokens_from_request("gpt-3.5-turbo", &request).unwrap(),
+            31
+        );
+
+        let request = ChatCompletionRequest {
+            messages: vec![user_message.clone()],
+            tools: vec![FunctionDefinition {
+                description: Some("Do a foo".to_string()),
+                ..foo.clone()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            num_tokens_from_request("gpt-3.5-turbo", &request).unwrap(),
+            36
+        );
+
+        let request = ChatCompletionRequest {
+            messages: vec![user_message],
+            tools: vec![foo],
+            tool_choice: Some(ToolChoice::Function("foo".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(
+            num_tokens_from_request("gpt-3.5-turbo", &request).unwrap(),
+            36
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "tools")]
+    fn test_num_tokens_from_request_with_system_message() {
+        let request = ChatCompletionRequest {
+            messages: vec![
+                ChatCompletionRequestMessage {
+                    role: "system".to_string(),
+                    content: Some("You are a helpful assistant.".into()),
+                    ..Default::default()
+                },
+                ChatCompletionRequestMessage {
+                    role: "user".to_string(),
+                    content: Some("What's the weather in Paris?".into()),
+                    ..Default::default()
+                },
+            ],
+            tools: vec![FunctionDefinition {
+                name: "get_weather".to_string(),
+                parameters: Some(serde_json::json!({
+                    "type": "object",
+                    "properties": { "city": { "type": "string" } },
+                })),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        // The system message is padded with a newline before the tool definitions
+        let mut padded = request.messages.clone();
+        padded[0].content.as_mut().unwrap().push_str("\n");
+        let messages_only = num_tokens_from_messages("gpt-4o", &padded).unwrap();
+        let with_tools = num_tokens_from_request("gpt-4o", &request).unwrap();
+        let bpe = bpe_for_model("gpt-4o").unwrap();
+        let tools = count_function_definitions(bpe, &request.tools);
+        assert_eq!(with_tools, messages_only + tools - 4);
+    }
+
+    #[test]
+    #[cfg(feature = "tools")]
+    fn test_num_tokens_from_request_with_response_format() {
+        let request = ChatCompletionRequest {
+            messages: vec![ChatCompletionRequestMessage {
+                role: "user".to_string(),
+                content: Some("Name a color.".into()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let json_schema = ResponseFormat::JsonSchema(crate::tools::JsonSchemaFormat {
+            name: "color".to_string(),
+            schema: Some(serde_json::json!({
+                "type": "object",
+                "properties": { "color": { "type": "string" } },
+                "required": ["color"],
+                "additionalProperties": false,
+            })),
+            strict: Some(true),
+            ..Default::default()
+        });
+        let with_format = |format| ChatCompletionRequest {
+            response_format: Some(format),
+            ..request.clone()
+        };
+        let plain = num_tokens_from_request("gpt-4o", &request).unwrap();
+        let bpe = bpe_for_model("gpt-4o").unwrap();
+        assert_eq!(
+            num_tokens_from_request("gpt-4o", &with_format(json_schema.clone())).unwrap(),
+            plain + count_response_format(bpe, &json_schema)
+        );
+        assert_eq!(
+            num_tokens_from_request("gpt-4o", &with_format(ResponseFormat::JsonObject)).unwrap(),
+            plain
+        );
+    }
+
+    #[test]
+    fn test_get_chat_completion_output_budget() {
+        let messages = vec![ChatCompletionRequestMessage {
+            role: "user".to_string(),
+            content: Some("Hello, world!".into()),
+            ..Default::default()
+        }];
+
+        // Non-reasoning model: capped at max output tokens, no reasoning reservation
+        let budget = get_chat_completion_output_budget("gpt-4o", &messages, None).unwrap();
+        assert_eq!(budget.max_completion_tokens, 16_384);
+        assert_eq!(budget.reasoning_tokens, 0);
+        assert_eq!(budget.visible_output_tokens, 16_384);
+        assert_eq!(
+            budget.prompt_tokens,
+            num_tokens_from_messages("gpt-4o", &messages).unwrap()
+        );
+
+        // Reasoning model: the reservation grows with effort
+        let low =
+            get_chat_completion_output_budget("o3", &messages, Some(ReasoningEffort::Low)).unwrap();
+        let high = get_chat_completion_output_budget("o3", &messages, Some(ReasoningEffort::High))
+            .unwrap();
+        assert_eq!(low.max_completion_tokens, 100_000);
+        assert!(high.reasoning_tokens > low.reasoning_tokens);
+        assert!(high.visible_output_tokens < low.visible_output_tokens);
+        let default = get_chat_completion_output_budget("o3", &messages, None).unwrap();
+        assert_eq!(
+            default.reasoning_tokens,
+            ReasoningEffort::Medium.estimated_reasoning_tokens()
+        );
+
+        // Small remaining context: the completion is capped at what is left
+        let gpt4 = get_chat_completion_output_budget("gpt-4", &messages, None).unwrap();
+        assert_eq!(gpt4.max_completion_tokens, 8192 - gpt4.prompt_tokens);
+
+        // Reasoning cannot exceed the available budget
+        let spark = get_chat_completion_output_budget(
+            "gpt-5.3-codex-spark",
+            &messages,
+            Some(ReasoningEffort::XHigh),
+        )
+        .unwrap();
+        assert_eq!(spark.max_completion_tokens, 32_768);
+        assert_eq!(spark.reasoning_tokens, 32_768);
+        assert_eq!(spark.visible_output_tokens, 0);
+    }
+
+    #[test]
+    fn test_estimate_chat_completion_cost() {
+        let messages = vec![ChatCompletionRequestMessage {
+            role: "user".to_string(),
+            content: Some("Hello, world!".into()),
+            ..Default::default()
+        }];
+        let prompt_tokens = num_tokens_from_messages("gpt-4o", &messages).unwrap();
+        let cost = estimate_chat_completion_cost("gpt-4o", &messages, Some(1000), None).unwrap();
+        assert_eq!(cost.input_tokens, prompt_tokens);
+        assert_eq!(cost.output_tokens, 1000);
+        assert_eq!(cost.output_cost, 0.01);
+
+        // Without an explicit output size, the model's maximum output size is assumed
+        let worst_case = estimate_chat_completion_cost("gpt-4o", &messages, None, None).unwrap();
+        assert_eq!(worst_case.output_tokens, 16_384);
+        assert!(worst_case.total_cost() > cost.total_cost());
+        let worst_case = estimate_chat_completion_cost("gpt-4.1", &messages, None, None).unwrap();
+        assert_eq!(
+            worst_case.output_tokens,
+            get_max_output_tokens("gpt-4.1").unwrap()
+        );
+
+        // ... unless the remaining context is smaller, or the maximum output size is unknown
+        let pricing = ModelPricing::new(1.0, None, 1.0);
+        let max_tokens = get_chat_completion_max_tokens("gpt-4", &messages).unwrap();
+        let worst_case =
+            estimate_chat_completion_cost("gpt-4", &messages, None, Some(pricing)).unwrap();
+        assert_eq!(worst_case.output_tokens, max_tokens);
+        let max_tokens = get_text_completion_max_tokens("davinci-002", "Hello").unwrap();
+        let worst_case =
+            estimate_text_completion_cost("davinci-002", "Hello", None, Some(pricing)).unwrap();
+        assert_eq!(worst_case.output_tokens, max_tokens);
+    }
+
+    #[test]
+    fn test_batch_embedding_inputs() {
+        let long_input = "hello ".repeat(9000);
+        let inputs = vec!["hello world", long_input.as_str(), "goodbye world"];
+        let plan = batch_embedding_inputs("text-embedding-3-large", &inputs).unwrap();
+        assert_eq!(plan.batches.len(), 1);
+        assert_eq!(plan.batches[0].indices, vec![0, 2]);
+        assert_eq!(plan.batches[0].num_tokens, 5);
+        assert_eq!(plan.oversized.len(), 1);
+        assert_eq!(plan.oversized[0].index, 1);
+        assert!(plan.oversized[0].num_tokens > 8192);
+
+        assert!(batch_embedding_inputs("gpt-4o", &inputs).is_err());
+    }
+
+    #[test]
+    fn test_plan_embedding_batches_respects_request_limits() {
+        let limits = EmbeddingLimits {
+            max_input_tokens: 10,
+            max_inputs_per_request: 3,
+            max_tokens_per_request: 20,
+        };
+        let plan = plan_embedding_batches(limits, vec![10, 10, 5, 1, 1, 1, 11, 1]);
+        let indices: Vec<_> = plan.batches.iter().map(|b| b.indices.clone()).collect();
+        assert_eq!(indices, vec![vec![0, 1], vec![2, 3, 4], vec![5, 7]]);
+        assert_eq!(
+            plan.oversized,
+            vec![OversizedEmbeddingInput {
+                index: 6,
+                num_tokens: 11
+            }]
+        );
+    }
+
+    #[test]
+    fn test_estimate_cost_with_custom_pricing() {
+        let pricing = ModelPricing::new(1.0, None, 1.0);
+        let cost = estimate_text_completion_cost("gpt-3.5-turbo", "Hello", Some(10), Some(pricing))
+            .unwrap();
+        assert_eq!(cost.input_tokens, 1);
+        assert!((cost.total_cost() - 11.0 / 1_000_000.0).abs() < 1e-12);
+
+        // Models without built-in pricing require explicit prices
+        assert!(estimate_text_completion_cost("gpt-oss-20b", "Hello", None, None).is_err());
+        assert!(estimate_text_completion_cost("gpt-oss-20b", "Hello", None, Some(pricing)).is_ok());
+    }
+
+    /// Caches the counts of a `CoreBPE` and records how many texts were encoded.
+    struct CachedCounter {
+        bpe: &'static CoreBPE,
+        cache: std::cell::RefCell<std::collections::HashMap<String, usize>>,
+        misses: std::cell::Cell<usize>,
+    }
+
+    impl TokenCounter for CachedCounter {
+        fn encode_ordinary(&self, text: &str) -> Vec<Rank> {
+            self.bpe.encode_ordinary(text)
+        }
+
+        fn encode_with_special_tokens(&self, text: &str) -> Vec<Rank> {
+            self.bpe.encode_with_special_tokens(text)
+        }
+
+        fn decode(&self, tokens: &[Rank]) -> Result<String> {
+            self.bpe.decode(tokens)
+        }
+
+        fn special_tokens(&self) -> std::collections::HashSet<&str> {
+            self.bpe.special_tokens()
+        }
+
+        fn count_with_special_tokens(&self, text: &str) -> usize {
+            *self
+                .cache
+                .borrow_mut()
+                .entry(text.to_string())
+                .or_insert_with(|| {
+                    self.misses.set(self.misses.get() + 1);
+                    self.bpe.count_with_special_tokens(text)
+                })
+        }
+    }
+
+    #[test]
+    fn test_counting_with_custom_counter() {
+        let counter = CachedCounter {
+            bpe: o200k_base_singleton(),
+            cache: Default::default(),
+            misses: Default::default(),
+        };
+        // Without the `tools` feature, every field is given
+        #[allow(clippy::needless_update)]
+        let request = ChatCompletionRequest {
+            messages: vec![
+                ChatCompletionRequestMessage {
+                    role: "system".to_string(),
+                    content: Some("You are terse.".into()),
+                    ..Default::default()
+                },
+                ChatCompletionRequestMessage {
+                    role: "user".to_string(),
+                    content: Some("What time is it?".into()),
+                    ..Default::default()
+                },
+            ],
+            #[cfg(feature = "tools")]
+            tools: vec![FunctionDefinition {
+                name: "get_time".to_string(),
+                ..Default::default()
+            }],
+            tool_choice: Some(ToolChoice::Function("get_time".to_string())),
+            ..Default::default()
+        };
+        let messages = &request.messages;
+        let gpt_4o = ChatCounter::with_counter("gpt-4o", &counter).unwrap();
+        assert_eq!(gpt_4o.model(), "gpt-4o");
+
+        assert_eq!(
+            gpt_4o.num_tokens_from_request(&request).unwrap(),
+            num_tokens_from_request("gpt-4o", &request).unwrap()
+        );
+        assert_eq!(
+            gpt_4o.num_tokens(messages).unwrap(),
+            num_tokens_from_messages("gpt-4o", messages).unwrap()
+        );
+        let misses = counter.misses.get();
+        assert_eq!(
+            gpt_4o.max_tokens(messages).unwrap(),
+            get_chat_completion_max_tokens("gpt-4o", messages).unwrap()
+        );
+        let o3_mini = ChatCounter::with_counter("o3-mini", &counter).unwrap();
+        assert_eq!(
+            o3_mini.output_budget(messages, None).unwrap(),
+            get_chat_completion_output_budget("o3-mini", messages, None).unwrap()
+        );
+        assert_eq!(counter.misses.get(), misses);
+
+        // Models without a known tokenizer can be counted, but have no context size
+        assert!(num_tokens_from_messages("my-model", messages).is_err());
+        let my_model = ChatCounter::with_counter("my-model", &counter).unwrap();
+        assert_eq!(
+            my_model.num_tokens(messages).unwrap(),
+            num_tokens_from_messages("gpt-4o", messages).unwrap()
+        );
+        assert!(my_model.max_tokens(messages).is_err());
+        // The text of files is encoded with the counter too
+        let with_file = ChatCompletionRequestMessage {
+            content: Some(vec![FileInput::new("Quarterly report", 0).into()].into()),
+            ..messages[1].clone()
+        };
+        let with_file = my_model.count_message(&with_file).unwrap();
+        assert_eq!(
+            with_file.media,
+            counter.count_with_special_tokens("Quarterly report")
+        );
+        assert!(ChatCounter::with_counter("text-embedding-3-small", &counter).is_err());
+    }
+
+    #[test]
+    fn test_estimate_for_other_providers() {
+        let messages = vec![ChatCompletionRequestMessage {
+            role: "user".to_string(),
+            content: Some("Summarize the attached report in three bullet points.".into()),
+            ..Default::default()
+        }];
+        let counter =
+            ChatCounter::with_counter("gemini-2.5-flash", o200k_base_singleton()).unwrap();
+        let exact = counter.num_tokens(&messages).unwrap();
+        let estimate = estimate_num_tokens_from_messages("gemini-2.5-flash", &messages).unwrap();
+        let profile = get_estimation_profile("gemini-2.5-flash").unwrap();
+        assert_eq!(estimate, profile.estimate(exact));
+        assert_eq!(
+            estimate_chat_completion_max_tokens("gemini-2.5-flash", &messages).unwrap(),
+            65_536
+        );
+        // Models without an output limit can use the rest of the context
+        let estimate = estimate_num_tokens_from_messages("llama-3.3-70b", &messages).unwrap();
+        assert_eq!(
+            estimate_chat_completion_max_tokens("llama-3.3-70b", &messages).unwrap(),
+            131_072 - estimate
+        );
+        // OpenAI models are counted exactly and have no profile
+        assert!(estimate_num_tokens_from_messages("gpt-4o", &messages).is_err());
+    }
 }
 
 /// This module provide support for working with the `async_openai` crate.
 #[cfg(feature = "async-openai")]
 pub mod async_openai {
-    use anyhow::Result;
+    use anyhow::{anyhow, Result};
     use async_openai::types::chat::{
-        ChatCompletionMessageToolCalls, ChatCompletionRequestAssistantMessageContent,
+        ChatCompletionFunctionCall, ChatCompletionMessageToolCalls,
+        ChatCompletionRequestAssistantMessageContent,
         ChatCompletionRequestAssistantMessageContentPart,
         ChatCompletionRequestDeveloperMessageContent,
         ChatCompletionRequestDeveloperMessageContentPart, ChatCompletionRequestMessage,
         ChatCompletionRequestSystemMessageContent, ChatCompletionRequestSystemMessageContentPart,
         ChatCompletionRequestToolMessageContent, ChatCompletionRequestToolMessageContentPart,
         ChatCompletionRequestUserMessageContent, ChatCompletionRequestUserMessageContentPart,
-        FunctionCall,
+        ChatCompletionToolChoiceOption, ChatCompletionTools, CreateChatCompletionRequest,
+        FileObject, FunctionCall, FunctionObject, ImageDetail, ImageUrl, InputAudio, Prompt,
+        ReasoningEffort, ResponseFormat, ResponseFormatJsonSchema,
+        ToolChoiceOptions as ChatToolChoiceOptions,
+    };
+
+    use async_openai::types::completions::CreateCompletionRequest;
+    use async_openai::types::embeddings::{CreateEmbeddingRequest, EmbeddingInput};
+    use async_openai::types::responses::{
+        CreateResponse, CustomToolCallOutputOutput, EasyInputContent, FunctionCallOutput,
+        InputContent, InputItem, InputParam, InputRole, Item, MessageItem, OutputMessageContent,
+        Role, SummaryPart, TextResponseFormatConfiguration, Tool, ToolChoiceOptions,
+        ToolChoiceParam,
+    };
+
+    use super::{
+        bpe_for_model, ChatCompletionRequestMessage as Message, ChatTokenBreakdown, ContentPart,
+        MessageContent, OutputBudget, OversizedEmbeddingInput,
+    };
+    use crate::model::{
+        get_context_size, get_embedding_limits, get_max_output_tokens, CostEstimate, ModelPricing,
     };
+    use crate::multimodal::{AudioInput, FileInput, ImageInput};
+    use crate::responses::{ResponseInputItem, ResponseRequest};
+
+    impl From<&FunctionObject> for crate::tools::FunctionDefinition {
+        fn from(f: &FunctionObject) -> Self {
+            Self {
+                name: f.name.clone(),
+                description: f.description.clone(),
+                parameters: f.parameters.clone(),
+            }
+        }
+    }
+
+    impl From<&ResponseFormatJsonSchema> for crate::tools::ResponseFormat {
+        fn from(f: &ResponseFormatJsonSchema) -> Self {
+            Self::JsonSchema(crate::tools::JsonSchemaFormat {
+                name: f.name.clone(),
+                description: f.description.clone(),
+                schema: f.schema.clone(),
+                strict: f.strict,
+            })
+        }
+    }
+
+    impl From<&ResponseFormat> for crate::tools::ResponseFormat {
+        fn from(f: &ResponseFormat) -> Self {
+            match f {
+                ResponseFormat::Text => Self::Text,
+                ResponseFormat::JsonObject => Self::JsonObject,
+                ResponseFormat::JsonSchema { json_schema } => json_schema.into(),
+            }
+        }
+    }
+
+    impl From<&ReasoningEffort> for super::ReasoningEffort {
+        fn from(e: &ReasoningEffort) -> Self {
+            match e {
+                ReasoningEffort::None => Self::None,
+                ReasoningEffort::Minimal => Self::Minimal,
+                ReasoningEffort::Low => Self::Low,
+                ReasoningEffort::Medium => Self::Medium,
+                ReasoningEffort::High => Self::High,
+                ReasoningEffort::Xhigh => Self::XHigh,
+            }
+        }
+    }
 
     impl From<&FunctionCall> for super::FunctionCall {
         fn from(f: &FunctionCall) -> Self {
@@ -575,209 +2396,886 @@ pub mod async_openai {
         }
     }
 
-    fn join_texts(texts: Vec<String>) -> Option<String> {
-        if texts.is_empty() {
+    fn text_parts<'a>(texts: impl Iterator<Item = &'a str>) -> MessageContent {
+        MessageContent::Parts(texts.map(ContentPart::from).collect())
+    }
+
+    fn system_content(content: &ChatCompletionRequestSystemMessageContent) -> MessageContent {
+        match content {
+            ChatCompletionRequestSystemMessageContent::Text(s) => s.as_str().into(),
+            ChatCompletionRequestSystemMessageContent::Array(parts) => text_parts(
+                parts
+                    .iter()
+                    .map(|ChatCompletionRequestSystemMessageContentPart::Text(t)| t.text.as_str()),
+            ),
+        }
+    }
+
+    fn developer_content(content: &ChatCompletionRequestDeveloperMessageContent) -> MessageContent {
+        match content {
+            ChatCompletionRequestDeveloperMessageContent::Text(s) => s.as_str().into(),
+            ChatCompletionRequestDeveloperMessageContent::Array(parts) => {
+                text_parts(parts.iter().map(
+                    |ChatCompletionRequestDeveloperMessageContentPart::Text(t)| t.text.as_str(),
+                ))
+            }
+        }
+    }
+
+    /// Converts user content, with the images, audio clips and files that `resolver` describes.
+    /// The others are skipped.
+    fn user_content<R>(
+        content: &ChatCompletionRequestUserMessageContent,
+        resolver: &R,
+    ) -> MessageContent
+    where
+        R: MediaResolver + ?Sized,
+    {
+        let parts = match content {
+            ChatCompletionRequestUserMessageContent::Text(s) => return s.as_str().into(),
+            ChatCompletionRequestUserMessageContent::Array(parts) => parts,
+        };
+        let parts = parts.iter().filter_map(|part| match part {
+            ChatCompletionRequestUserMessageContentPart::Text(t) => Some(t.text.as_str().into()),
+            ChatCompletionRequestUserMessageContentPart::ImageUrl(image) => {
+                let (width, height) = resolver.image_size(&image.image_url)?;
+                let detail = match image.image_url.detail {
+                    Some(ImageDetail::Low) => crate::multimodal::ImageDetail::Low,
+                    Some(ImageDetail::High | ImageDetail::Original) => {
+                        crate::multimodal::ImageDetail::High
+                    }
+                    Some(ImageDetail::Auto) | None => crate::multimodal::ImageDetail::Auto,
+                };
+                Some(
+                    ImageInput {
+                        width,
+                        height,
+                        detail,
+                    }
+                    .into(),
+                )
+            }
+            ChatCompletionRequestUserMessageContentPart::InputAudio(audio) => resolver
+                .audio_duration(&audio.input_audio)
+                .map(|duration| AudioInput::new(duration).into()),
+            ChatCompletionRequestUserMessageContentPart::File(file) => {
+                resolver.file_input(&file.file).map(ContentPart::from)
+            }
+        });
+        MessageContent::Parts(parts.collect())
+    }
+
+    fn assistant_content(content: &ChatCompletionRequestAssistantMessageContent) -> MessageContent {
+        match content {
+            ChatCompletionRequestAssistantMessageContent::Text(s) => s.as_str().into(),
+            ChatCompletionRequestAssistantMessageContent::Array(parts) => MessageContent::Parts(
+                parts
+                    .iter()
+                    .map(|part| match part {
+                        ChatCompletionRequestAssistantMessageContentPart::Text(t) => {
+                            t.text.as_str().into()
+                        }
+                        ChatCompletionRequestAssistantMessageContentPart::Refusal(r) => {
+                            ContentPart::Refusal(r.refusal.clone())
+                        }
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    fn tool_content(content: &ChatCompletionRequestToolMessageContent) -> MessageContent {
+        match content {
+            ChatCompletionRequestToolMessageContent::Text(s) => s.as_str().into(),
+            ChatCompletionRequestToolMessageContent::Array(parts) => text_parts(
+                parts
+                    .iter()
+                    .map(|ChatCompletionRequestToolMessageContentPart::Text(t)| t.text.as_str()),
+            ),
+        }
+    }
+
+    fn extract_tool_calls(
+        tool_calls: &Option<Vec<ChatCompletionMessageToolCalls>>,
+    ) -> Vec<super::ToolCall> {
+        tool_calls
+            .as_ref()
+            .map(|calls| {
+                calls
+                    .iter()
+                    .map(|tc| match tc {
+                        ChatCompletionMessageToolCalls::Function(f) => super::ToolCall::Function {
+                            id: f.id.clone(),
+                            function: (&f.function).into(),
+                        },
+                        ChatCompletionMessageToolCalls::Custom(c) => super::ToolCall::custom(
+                            c.id.clone(),
+                            c.custom_tool.name.clone(),
+                            c.custom_tool.input.clone(),
+                        ),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[allow(deprecated)]
+    impl From<&ChatCompletionRequestMessage> for super::ChatCompletionRequestMessage {
+        fn from(m: &ChatCompletionRequestMessage) -> Self {
+            match m {
+                ChatCompletionRequestMessage::System(msg) => Self {
+                    role: "system".to_string(),
+                    name: msg.name.clone(),
+                    content: Some(system_content(&msg.content)),
+                    ..Default::default()
+                },
+                ChatCompletionRequestMessage::Developer(msg) => Self {
+                    role: "developer".to_string(),
+                    name: msg.name.clone(),
+                    content: Some(developer_content(&msg.content)),
+                    ..Default::default()
+                },
+                ChatCompletionRequestMessage::User(msg) => Self {
+                    role: "user".to_string(),
+                    name: msg.name.clone(),
+                    content: Some(user_content(&msg.content, &NoMedia)),
+                    ..Default::default()
+                },
+                ChatCompletionRequestMessage::Assistant(msg) => Self {
+                    role: "assistant".to_string(),
+                    name: msg.name.clone(),
+                    content: msg.content.as_ref().map(assistant_content),
+                    function_call: msg.function_call.as_ref().map(|f| f.into()),
+                    tool_calls: extract_tool_calls(&msg.tool_calls),
+                    refusal: msg.refusal.clone(),
+                    ..Default::default()
+                },
+                ChatCompletionRequestMessage::Tool(msg) => Self {
+                    role: "tool".to_string(),
+                    tool_call_id: Some(msg.tool_call_id.clone()),
+                    content: Some(tool_content(&msg.content)),
+                    ..Default::default()
+                },
+                ChatCompletionRequestMessage::Function(msg) => Self {
+                    role: "function".to_string(),
+                    name: Some(msg.name.clone()),
+                    content: msg.content.clone().map(Into::into),
+                    ..Default::default()
+                },
+            }
+        }
+    }
+
+    /// Calculates the total number of tokens for the given list of messages.
+    ///
+    /// **Note:** Only text content is counted. Non-text parts (images, audio, files) are
+    /// silently skipped because they use a separate token formula based on resolution/duration,
+    /// not BPE encoding. If your messages contain non-text content, the returned count will
+    /// be lower than the actual API token usage. Use [`num_tokens_from_messages_with_media`]
+    /// to include the non-text parts whose size you know.
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - A string slice representing the name of the model.
+    /// * `messages` - A slice of `async_openai::types::ChatCompletionRequestMessage` instances.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the total number of tokens (`usize`) or an error if the calculation fails.
+    pub fn num_tokens_from_messages(
+        model: &str,
+        messages: &[ChatCompletionRequestMessage],
+    ) -> Result<usize> {
+        let messages: Vec<super::ChatCompletionRequestMessage> =
+            messages.iter().map(|m| m.into()).collect();
+        super::num_tokens_from_messages(model, &messages)
+    }
+
+    /// Calculates the tokens of each message by field.
+    ///
+    /// As with [`num_tokens_from_messages`], only text content is counted.
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - A string slice representing the name of the model.
+    /// * `messages` - A slice of `async_openai::types::ChatCompletionRequestMessage` instances.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the breakdown or an error if the calculation fails.
+    pub fn num_tokens_breakdown_from_messages(
+        model: &str,
+        messages: &[ChatCompletionRequestMessage],
+    ) -> Result<ChatTokenBreakdown> {
+        let messages: Vec<super::ChatCompletionRequestMessage> =
+            messages.iter().map(|m| m.into()).collect();
+        super::num_tokens_breakdown_from_messages(model, &messages)
+    }
+
+    /// Describes the non-text parts of user messages, which carry no size information.
+    ///
+    /// Implementations typically look the part up in a local cache or in the metadata of
+    /// the uploaded file. Parts for which a method returns `None` are skipped when counting.
+    pub trait MediaResolver {
+        /// Returns the `(width, height)` of an image in pixels.
+        fn image_size(&self, _image: &ImageUrl) -> Option<(u32, u32)> {
             None
-        } else {
-            Some(texts.join(""))
         }
+
+        /// Returns the duration of an audio clip.
+        fn audio_duration(&self, _audio: &InputAudio) -> Option<std::time::Duration> {
+            None
+        }
+
+        /// Returns the extracted text and page images of a file.
+        fn file_input(&self, _file: &FileObject) -> Option<FileInput> {
+            None
+        }
+    }
+
+    /// Describes no media, so that only text parts are converted.
+    struct NoMedia;
+
+    impl MediaResolver for NoMedia {}
+
+    struct ImageSizeResolver<F>(F);
+
+    impl<F> MediaResolver for ImageSizeResolver<F>
+    where
+        F: Fn(&ImageUrl) -> Option<(u32, u32)>,
+    {
+        fn image_size(&self, image: &ImageUrl) -> Option<(u32, u32)> {
+            (self.0)(image)
+        }
+    }
+
+    /// Calculates the total number of tokens for the given list of messages, including images.
+    ///
+    /// Image URLs carry no dimensions, so `image_size` is called with each image part of a
+    /// user message and should return its `(width, height)` in pixels, e.g. from a local cache
+    /// or the metadata of the uploaded file. Images for which it returns `None` are skipped,
+    /// as in [`num_tokens_from_messages`]. Audio and file parts are skipped; use
+    /// [`num_tokens_from_messages_with_media`] to count them too.
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - A string slice representing the name of the model.
+    /// * `messages` - A slice of `async_openai::types::ChatCompletionRequestMessage` instances.
+    /// * `image_size` - Returns the dimensions of an image, if known.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the total number of tokens (`usize`) or an error if the calculation fails.
+    pub fn num_tokens_from_messages_with_images<F>(
+        model: &str,
+        messages: &[ChatCompletionRequestMessage],
+        image_size: F,
+    ) -> Result<usize>
+    where
+        F: Fn(&ImageUrl) -> Option<(u32, u32)>,
+    {
+        num_tokens_from_messages_with_media(model, messages, &ImageSizeResolver(image_size))
+    }
+
+    /// Calculates the total number of tokens for the given list of messages, including images,
+    /// audio and files.
+    ///
+    /// The `resolver` is asked for the size of each non-text part of a user message. Parts it
+    /// cannot describe are skipped, as in [`num_tokens_from_messages`].
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - A string slice representing the name of the model.
+    /// * `messages` - A slice of `async_openai::types::ChatCompletionRequestMessage` instances.
+    /// * `resolver` - Describes the images, audio clips and files of the messages.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the total number of tokens (`usize`) or an error if the calculation
+    ///   fails, e.g. because the model does not accept one of the resolved input types.
+    pub fn num_tokens_from_messages_with_media<R>(
+        model: &str,
+        messages: &[ChatCompletionRequestMessage],
+        resolver: &R,
+    ) -> Result<usize>
+    where
+        R: MediaResolver + ?Sized,
+    {
+        let messages: Vec<super::ChatCompletionRequestMessage> = messages
+            .iter()
+            .map(|m| {
+                let mut converted: super::ChatCompletionRequestMessage = m.into();
+                if let ChatCompletionRequestMessage::User(msg) = m {
+                    converted.content = Some(user_content(&msg.content, resolver));
+                }
+                converted
+            })
+            .collect();
+        super::num_tokens_from_messages(model, &messages)
+    }
+
+    /// Retrieves the maximum token limit for chat completions.
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - A string slice representing the name of the model.
+    /// * `messages` - A slice of `async_openai::types::ChatCompletionRequestMessage` instances.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the maximum number of tokens (`usize`) allowed for chat completions or an error if the retrieval fails.
+    pub fn get_chat_completion_max_tokens(
+        model: &str,
+        messages: &[ChatCompletionRequestMessage],
+    ) -> Result<usize> {
+        let messages: Vec<super::ChatCompletionRequestMessage> =
+            messages.iter().map(|m| m.into()).collect();
+        super::get_chat_completion_max_tokens(model, &messages)
+    }
+
+    /// Calculates the output budget of a chat completion request, accounting for reasoning tokens.
+    ///
+    /// See [`super::get_chat_completion_output_budget`] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - A string slice representing the name of the model.
+    /// * `messages` - A slice of `async_openai::types::ChatCompletionRequestMessage` instances.
+    /// * `reasoning_effort` - The `reasoning_effort` of the request, if any.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the [`OutputBudget`] or an error if the calculation fails.
+    pub fn get_chat_completion_output_budget(
+        model: &str,
+        messages: &[ChatCompletionRequestMessage],
+        reasoning_effort: Option<&ReasoningEffort>,
+    ) -> Result<OutputBudget> {
+        let messages: Vec<super::ChatCompletionRequestMessage> =
+            messages.iter().map(|m| m.into()).collect();
+        super::get_chat_completion_output_budget(
+            model,
+            &messages,
+            reasoning_effort.map(|e| e.into()),
+        )
+    }
+
+    /// Estimates the cost of a chat completion request.
+    ///
+    /// See [`super::estimate_chat_completion_cost`] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - A string slice representing the name of the model.
+    /// * `messages` - A slice of `async_openai::types::ChatCompletionRequestMessage` instances.
+    /// * `max_output_tokens` - The expected number of output tokens, or `None` for the worst case.
+    /// * `pricing` - Prices to use instead of the built-in table.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the [`CostEstimate`] or an error if the estimation fails.
+    pub fn estimate_chat_completion_cost(
+        model: &str,
+        messages: &[ChatCompletionRequestMessage],
+        max_output_tokens: Option<usize>,
+        pricing: Option<ModelPricing>,
+    ) -> Result<CostEstimate> {
+        let messages: Vec<super::ChatCompletionRequestMessage> =
+            messages.iter().map(|m| m.into()).collect();
+        super::estimate_chat_completion_cost(model, &messages, max_output_tokens, pricing)
     }
 
-    fn system_content_text(content: &ChatCompletionRequestSystemMessageContent) -> Option<String> {
-        match content {
-            ChatCompletionRequestSystemMessageContent::Text(s) => Some(s.clone()),
-            ChatCompletionRequestSystemMessageContent::Array(parts) => join_texts(
-                parts
-                    .iter()
-                    .map(|ChatCompletionRequestSystemMessageContentPart::Text(t)| t.text.clone())
-                    .collect(),
-            ),
-        }
+    fn input_content_text(content: &[InputContent]) -> String {
+        content
+            .iter()
+            .filter_map(|part| match part {
+                InputContent::InputText(t) => Some(t.text.as_str()),
+                InputContent::InputImage(_) | InputContent::InputFile(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join("")
     }
 
-    fn developer_content_text(
-        content: &ChatCompletionRequestDeveloperMessageContent,
-    ) -> Option<String> {
-        match content {
-            ChatCompletionRequestDeveloperMessageContent::Text(s) => Some(s.clone()),
-            ChatCompletionRequestDeveloperMessageContent::Array(parts) => join_texts(
-                parts
-                    .iter()
-                    .map(|ChatCompletionRequestDeveloperMessageContentPart::Text(t)| t.text.clone())
-                    .collect(),
-            ),
-        }
+    fn response_message(role: &str, content: String) -> ResponseInputItem {
+        ResponseInputItem::message(role, content)
     }
 
-    fn user_content_text(content: &ChatCompletionRequestUserMessageContent) -> Option<String> {
-        match content {
-            ChatCompletionRequestUserMessageContent::Text(s) => Some(s.clone()),
-            ChatCompletionRequestUserMessageContent::Array(parts) => join_texts(
-                parts
+    fn response_input_item(item: &InputItem) -> Option<ResponseInputItem> {
+        let item = match item {
+            InputItem::ItemReference(_) => return None,
+            InputItem::EasyMessage(message) => {
+                let role = match message.role {
+                    Role::User => "user",
+                    Role::Assistant => "assistant",
+                    Role::System => "system",
+                    Role::Developer => "developer",
+                };
+                let content = match &message.content {
+                    EasyInputContent::Text(text) => text.clone(),
+                    EasyInputContent::ContentList(parts) => input_content_text(parts),
+                };
+                return Some(response_message(role, content));
+            }
+            InputItem::Item(item) => item,
+        };
+        match item {
+            Item::Message(MessageItem::Input(message)) => {
+                let role = match message.role {
+                    InputRole::User => "user",
+                    InputRole::System => "system",
+                    InputRole::Developer => "developer",
+                };
+                Some(response_message(role, input_content_text(&message.content)))
+            }
+            Item::Message(MessageItem::Output(message)) => {
+                let parts = message
+                    .content
                     .iter()
-                    .filter_map(|p| match p {
-                        ChatCompletionRequestUserMessageContentPart::Text(t) => {
-                            Some(t.text.clone())
-                        }
-                        ChatCompletionRequestUserMessageContentPart::ImageUrl(_)
-                        | ChatCompletionRequestUserMessageContentPart::InputAudio(_)
-                        | ChatCompletionRequestUserMessageContentPart::File(_) => None,
+                    .map(|part| match part {
+                        OutputMessageContent::OutputText(t) => t.text.as_str().into(),
+                        OutputMessageContent::Refusal(r) => ContentPart::Refusal(r.refusal.clone()),
                     })
+                    .collect();
+                Some(ResponseInputItem::Message(Message {
+                    role: "assistant".to_string(),
+                    content: Some(MessageContent::Parts(parts)),
+                    ..Default::default()
+                }))
+            }
+            Item::FunctionCall(call) => Some(ResponseInputItem::FunctionCall {
+                call_id: call.call_id.clone(),
+                name: call.name.clone(),
+                arguments: call.arguments.clone(),
+            }),
+            Item::CustomToolCall(call) => Some(ResponseInputItem::FunctionCall {
+                call_id: call.call_id.clone(),
+                name: call.name.clone(),
+                arguments: call.input.clone(),
+            }),
+            Item::FunctionCallOutput(output) => Some(ResponseInputItem::FunctionCallOutput {
+                call_id: output.call_id.clone(),
+                output: match &output.output {
+                    FunctionCallOutput::Text(text) => text.clone(),
+                    FunctionCallOutput::Content(parts) => input_content_text(parts),
+                },
+            }),
+            Item::CustomToolCallOutput(output) => Some(ResponseInputItem::FunctionCallOutput {
+                call_id: output.call_id.clone(),
+                output: match &output.output {
+                    CustomToolCallOutputOutput::Text(text) => text.clone(),
+                    CustomToolCallOutputOutput::List(parts) => input_content_text(parts),
+                },
+            }),
+            Item::Reasoning(reasoning) => Some(ResponseInputItem::Reasoning {
+                summary: reasoning
+                    .summary
+                    .iter()
+                    .map(|SummaryPart::SummaryText(s)| s.text.clone())
                     .collect(),
-            ),
+            }),
+            _ => None,
         }
     }
 
-    fn assistant_content_text(
-        content: &ChatCompletionRequestAssistantMessageContent,
-    ) -> (Option<String>, Option<String>) {
-        match content {
-            ChatCompletionRequestAssistantMessageContent::Text(s) => (Some(s.clone()), None),
-            ChatCompletionRequestAssistantMessageContent::Array(parts) => {
-                let mut texts = Vec::new();
-                let mut refusals = Vec::new();
-                for p in parts {
-                    match p {
-                        ChatCompletionRequestAssistantMessageContentPart::Text(t) => {
-                            texts.push(t.text.clone());
-                        }
-                        ChatCompletionRequestAssistantMessageContentPart::Refusal(r) => {
-                            refusals.push(r.refusal.clone());
-                        }
+    impl From<&CreateResponse> for ResponseRequest {
+        fn from(request: &CreateResponse) -> Self {
+            let input = match &request.input {
+                InputParam::Text(text) => vec![response_message("user", text.clone())],
+                InputParam::Items(items) => items.iter().filter_map(response_input_item).collect(),
+            };
+            let tools = request
+                .tools
+                .iter()
+                .flatten()
+                .filter_map(|tool| match tool {
+                    Tool::Function(f) => Some(crate::tools::FunctionDefinition {
+                        name: f.name.clone(),
+                        description: f.description.clone(),
+                        parameters: f.parameters.clone(),
+                    }),
+                    Tool::Custom(c) => Some(crate::tools::FunctionDefinition {
+                        name: c.name.clone(),
+                        description: c.description.clone(),
+                        parameters: None,
+                    }),
+                    _ => None,
+                })
+                .collect();
+            let tool_choice = request
+                .tool_choice
+                .as_ref()
+                .and_then(|choice| match choice {
+                    ToolChoiceParam::Mode(ToolChoiceOptions::None) => Some(super::ToolChoice::None),
+                    ToolChoiceParam::Mode(ToolChoiceOptions::Auto) => Some(super::ToolChoice::Auto),
+                    ToolChoiceParam::Mode(ToolChoiceOptions::Required) => {
+                        Some(super::ToolChoice::Required)
                     }
+                    ToolChoiceParam::Function(f) => {
+                        Some(super::ToolChoice::Function(f.name.clone()))
+                    }
+                    _ => None,
+                });
+            let response_format = request.text.as_ref().map(|text| match &text.format {
+                TextResponseFormatConfiguration::Text => crate::tools::ResponseFormat::Text,
+                TextResponseFormatConfiguration::JsonObject => {
+                    crate::tools::ResponseFormat::JsonObject
                 }
-                (join_texts(texts), join_texts(refusals))
+                TextResponseFormatConfiguration::JsonSchema(schema) => schema.into(),
+            });
+            Self {
+                instructions: request.instructions.clone(),
+                input,
+                tools,
+                tool_choice,
+                response_format,
             }
         }
     }
 
-    fn tool_content_text(content: &ChatCompletionRequestToolMessageContent) -> Option<String> {
-        match content {
-            ChatCompletionRequestToolMessageContent::Text(s) => Some(s.clone()),
-            ChatCompletionRequestToolMessageContent::Array(parts) => join_texts(
-                parts
-                    .iter()
-                    .map(|ChatCompletionRequestToolMessageContentPart::Text(t)| t.text.clone())
-                    .collect(),
-            ),
-        }
+    /// Calculates the number of input tokens of a Responses API request.
+    ///
+    /// Only text is counted: image and file parts, item references and the items of built-in
+    /// tools are skipped, as are built-in tool definitions. See
+    /// [`crate::responses::num_tokens_from_response_request`] for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - A string slice representing the name of the model.
+    /// * `request` - An `async_openai::types::responses::CreateResponse`.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the number of input tokens (`usize`) or an error if the calculation fails.
+    pub fn num_tokens_from_response_request(
+        model: &str,
+        request: &CreateResponse,
+    ) -> Result<usize> {
+        crate::responses::num_tokens_from_response_request(model, &request.into())
     }
 
-    fn extract_tool_calls(
-        tool_calls: &Option<Vec<ChatCompletionMessageToolCalls>>,
-    ) -> Vec<super::FunctionCall> {
-        tool_calls
-            .as_ref()
-            .map(|calls| {
-                calls
+    #[allow(deprecated)]
+    impl From<&CreateChatCompletionRequest> for super::ChatCompletionRequest {
+        /// Converts the parts of the request that consume prompt tokens. Custom tools are
+        /// counted like functions without parameters, and allowed tools choices are skipped.
+        fn from(request: &CreateChatCompletionRequest) -> Self {
+            let tools = match (&request.tools, &request.functions) {
+                (Some(tools), _) => tools
                     .iter()
-                    .map(|tc| match tc {
-                        ChatCompletionMessageToolCalls::Function(f) => (&f.function).into(),
-                        ChatCompletionMessageToolCalls::Custom(c) => super::FunctionCall {
-                            name: c.custom_tool.name.clone(),
-                            arguments: c.custom_tool.input.clone(),
+                    .map(|tool| match tool {
+                        ChatCompletionTools::Function(f) => (&f.function).into(),
+                        ChatCompletionTools::Custom(c) => crate::tools::FunctionDefinition {
+                            name: c.custom.name.clone(),
+                            description: c.custom.description.clone(),
+                            parameters: None,
                         },
                     })
-                    .collect()
-            })
-            .unwrap_or_default()
+                    .collect(),
+                (None, Some(functions)) => functions
+                    .iter()
+                    .map(|f| crate::tools::FunctionDefinition {
+                        name: f.name.clone(),
+                        description: f.description.clone(),
+                        parameters: Some(f.parameters.clone()),
+                    })
+                    .collect(),
+                (None, None) => Vec::new(),
+            };
+            let tool_choice = match (&request.tool_choice, &request.function_call) {
+                (Some(choice), _) => match choice {
+                    ChatCompletionToolChoiceOption::Mode(ChatToolChoiceOptions::None) => {
+                        Some(super::ToolChoice::None)
+                    }
+                    ChatCompletionToolChoiceOption::Mode(ChatToolChoiceOptions::Auto) => {
+                        Some(super::ToolChoice::Auto)
+                    }
+                    ChatCompletionToolChoiceOption::Mode(ChatToolChoiceOptions::Required) => {
+                        Some(super::ToolChoice::Required)
+                    }
+                    ChatCompletionToolChoiceOption::Function(f) => {
+                        Some(super::ToolChoice::Function(f.function.name.clone()))
+                    }
+                    ChatCompletionToolChoiceOption::Custom(c) => {
+                        Some(super::ToolChoice::Function(c.custom.name.clone()))
+                    }
+                    ChatCompletionToolChoiceOption::AllowedTools(_) => None,
+                },
+                (None, Some(call)) => Some(match call {
+                    ChatCompletionFunctionCall::None => super::ToolChoice::None,
+                    ChatCompletionFunctionCall::Auto => super::ToolChoice::Auto,
+                    ChatCompletionFunctionCall::Function { name } => {
+                        super::ToolChoice::Function(name.clone())
+                    }
+                }),
+                (None, None) => None,
+            };
+            Self {
+                messages: request.messages.iter().map(|m| m.into()).collect(),
+                tools,
+                tool_choice,
+                response_format: request.response_format.as_ref().map(|f| f.into()),
+            }
+        }
+    }
+
+    /// The token budget of a chat completion request that passed
+    /// [`check_chat_completion_request`].
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+    pub struct ChatRequestReport {
+        /// Number of tokens in the prompt: messages, tools and response format.
+        pub prompt_tokens: usize,
+        /// The context size of the model.
+        pub context_size: usize,
+        /// Maximum number of tokens the model can generate for this request: the rest of the
+        /// context, capped at the model's maximum output size.
+        pub available_completion_tokens: usize,
+        /// The `max_completion_tokens` (or legacy `max_tokens`) of the request, if set.
+        pub requested_completion_tokens: Option<usize>,
+    }
+
+    /// Why [`check_chat_completion_request`] rejected a request.
+    ///
+    /// The error is returned inside an [`anyhow::Error`] and can be recovered with
+    /// [`anyhow::Error::downcast_ref`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ChatRequestError {
+        /// The prompt does not fit in the model's context.
+        PromptTooLong {
+            /// Number of tokens in the prompt.
+            prompt_tokens: usize,
+            /// The context size of the model.
+            context_size: usize,
+        },
+        /// The requested completion size is more than the model can generate for this prompt.
+        CompletionTooLong {
+            /// The `max_completion_tokens` (or legacy `max_tokens`) of the request.
+            requested_completion_tokens: usize,
+            /// Maximum number of tokens the model can generate for this request.
+            available_completion_tokens: usize,
+        },
+    }
+
+    impl std::fmt::Display for ChatRequestError {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            match self {
+                Self::PromptTooLong {
+                    prompt_tokens,
+                    context_size,
+                } => write!(
+                    f,
+                    "Prompt of {} tokens exceeds the context size of {} tokens",
+                    prompt_tokens, context_size
+                ),
+                Self::CompletionTooLong {
+                    requested_completion_tokens,
+                    available_completion_tokens,
+                } => write!(
+                    f,
+                    "Requested {} completion tokens, but only {} are available",
+                    requested_completion_tokens, available_completion_tokens
+                ),
+            }
+        }
     }
 
+    impl std::error::Error for ChatRequestError {}
+
+    /// Checks that a chat completion request fits the model before it is sent.
+    ///
+    /// The prompt is counted with [`super::num_tokens_from_request`] (messages, tools and
+    /// response format). The request is rejected if the prompt does not fit in the model's
+    /// context, or if its `max_completion_tokens` (or legacy `max_tokens`) is more than the
+    /// rest of the context or the model's maximum output size.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - An `async_openai::types::chat::CreateChatCompletionRequest`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ChatRequestError`] if the request does not fit, or an error if the request
+    /// cannot be counted or the context size of the model is unknown.
     #[allow(deprecated)]
-    impl From<&ChatCompletionRequestMessage> for super::ChatCompletionRequestMessage {
-        fn from(m: &ChatCompletionRequestMessage) -> Self {
-            match m {
-                ChatCompletionRequestMessage::System(msg) => Self {
-                    role: "system".to_string(),
-                    name: msg.name.clone(),
-                    content: Some(system_content_text(&msg.content).unwrap_or_default()),
-                    ..Default::default()
-                },
-                ChatCompletionRequestMessage::Developer(msg) => Self {
-                    role: "developer".to_string(),
-                    name: msg.name.clone(),
-                    content: Some(developer_content_text(&msg.content).unwrap_or_default()),
-                    ..Default::default()
-                },
-                ChatCompletionRequestMessage::User(msg) => Self {
-                    role: "user".to_string(),
-                    name: msg.name.clone(),
-                    content: Some(user_content_text(&msg.content).unwrap_or_default()),
-                    ..Default::default()
-                },
-                ChatCompletionRequestMessage::Assistant(msg) => {
-                    let (content, refusal) = msg
-                        .content
-                        .as_ref()
-                        .map(assistant_content_text)
-                        .unwrap_or_default();
-                    let refusal = refusal.or_else(|| msg.refusal.clone());
-                    Self {
-                        role: "assistant".to_string(),
-                        name: msg.name.clone(),
-                        content,
-                        function_call: msg.function_call.as_ref().map(|f| f.into()),
-                        tool_calls: extract_tool_calls(&msg.tool_calls),
-                        refusal,
-                    }
+    pub fn check_chat_completion_request(
+        request: &CreateChatCompletionRequest,
+    ) -> Result<ChatRequestReport> {
+        let model = request.model.as_str();
+        let context_size = get_context_size(model)
+            .ok_or_else(|| anyhow!("Unknown context size for model {}", model))?;
+        let prompt_tokens = super::num_tokens_from_request(model, &request.into())?;
+        if prompt_tokens > context_size {
+            return Err(ChatRequestError::PromptTooLong {
+                prompt_tokens,
+                context_size,
+            }
+            .into());
+        }
+        let mut available_completion_tokens = context_size - prompt_tokens;
+        if let Some(max_output_tokens) = get_max_output_tokens(model) {
+            available_completion_tokens = available_completion_tokens.min(max_output_tokens);
+        }
+        let requested_completion_tokens = request
+            .max_completion_tokens
+            .or(request.max_tokens)
+            .map(|n| n as usize);
+        if let Some(requested) = requested_completion_tokens {
+            if requested > available_completion_tokens {
+                return Err(ChatRequestError::CompletionTooLong {
+                    requested_completion_tokens: requested,
+                    available_completion_tokens,
                 }
-                ChatCompletionRequestMessage::Tool(msg) => Self {
-                    role: "tool".to_string(),
-                    name: Some(msg.tool_call_id.clone()),
-                    content: Some(tool_content_text(&msg.content).unwrap_or_default()),
-                    ..Default::default()
-                },
-                ChatCompletionRequestMessage::Function(msg) => Self {
-                    role: "function".to_string(),
-                    name: Some(msg.name.clone()),
-                    content: msg.content.clone(),
-                    ..Default::default()
-                },
+                .into());
             }
         }
+        Ok(ChatRequestReport {
+            prompt_tokens,
+            context_size,
+            available_completion_tokens,
+            requested_completion_tokens,
+        })
     }
 
-    /// Calculates the total number of tokens for the given list of messages.
+    /// The token counts of an embeddings request, as returned by
+    /// [`num_tokens_from_embedding_request`].
+    #[derive(Debug, Default, Clone, PartialEq, Eq)]
+    pub struct EmbeddingRequestTokens {
+        /// Number of tokens of each input, in order.
+        pub input_tokens: Vec<usize>,
+        /// Number of tokens summed over all inputs.
+        pub total_tokens: usize,
+        /// Inputs that exceed the model's per-input token limit.
+        pub oversized: Vec<OversizedEmbeddingInput>,
+        /// Whether the request has more inputs than the model accepts in a single request.
+        pub exceeds_max_inputs: bool,
+        /// Whether the total number of tokens exceeds the model's per-request limit.
+        pub exceeds_max_tokens: bool,
+    }
+
+    impl EmbeddingRequestTokens {
+        /// Returns `true` if the request is within all of the model's limits.
+        pub fn is_within_limits(&self) -> bool {
+            self.oversized.is_empty() && !self.exceeds_max_inputs && !self.exceeds_max_tokens
+        }
+    }
+
+    /// Counts the tokens of each input of an embeddings request and checks them against the
+    /// model's limits (see [`get_embedding_limits`]).
     ///
-    /// **Note:** Only text content is counted. Non-text parts (images, audio, files) are
-    /// silently skipped because they use a separate token formula based on resolution/duration,
-    /// not BPE encoding. If your messages contain non-text content, the returned count will
-    /// be lower than the actual API token usage.
+    /// String inputs are encoded with the model's tokenizer. Inputs given as token arrays are
+    /// counted by their length.
     ///
     /// # Arguments
     ///
-    /// * `model` - A string slice representing the name of the model.
-    /// * `messages` - A slice of `async_openai::types::ChatCompletionRequestMessage` instances.
+    /// * `request` - An `async_openai::types::embeddings::CreateEmbeddingRequest`.
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// * A `Result` containing the total number of tokens (`usize`) or an error if the calculation fails.
-    pub fn num_tokens_from_messages(
-        model: &str,
-        messages: &[ChatCompletionRequestMessage],
-    ) -> Result<usize> {
-        let messages: Vec<super::ChatCompletionRequestMessage> =
-            messages.iter().map(|m| m.into()).collect();
-        super::num_tokens_from_messages(model, &messages)
+    /// Returns an error if the model is not a known embedding model or has no tokenizer.
+    pub fn num_tokens_from_embedding_request(
+        request: &CreateEmbeddingRequest,
+    ) -> Result<EmbeddingRequestTokens> {
+        let model = request.model.as_str();
+        let limits = get_embedding_limits(model)
+            .ok_or_else(|| anyhow!("Unknown embedding limits for model {}", model))?;
+        let input_tokens: Vec<usize> = match &request.input {
+            EmbeddingInput::String(input) => {
+                vec![bpe_for_model(model)?.count_with_special_tokens(input)]
+            }
+            EmbeddingInput::StringArray(inputs) => {
+                let bpe = bpe_for_model(model)?;
+                inputs
+                    .iter()
+                    .map(|input| bpe.count_with_special_tokens(input))
+                    .collect()
+            }
+            EmbeddingInput::IntegerArray(tokens) => vec![tokens.len()],
+            EmbeddingInput::ArrayOfIntegerArray(inputs) => inputs.iter().map(Vec::len).collect(),
+        };
+        let total_tokens = input_tokens.iter().sum();
+        let oversized = input_tokens
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &num_tokens)| {
+                super::oversized_embedding_input(&limits, index, num_tokens)
+            })
+            .collect();
+        Ok(EmbeddingRequestTokens {
+            exceeds_max_inputs: input_tokens.len() > limits.max_inputs_per_request,
+            exceeds_max_tokens: total_tokens > limits.max_tokens_per_request,
+            input_tokens,
+            total_tokens,
+            oversized,
+        })
     }
 
-    /// Retrieves the maximum token limit for chat completions.
+    /// The `max_tokens` the API uses when a completion request does not set it.
+    const DEFAULT_COMPLETION_MAX_TOKENS: usize = 16;
+
+    /// The token counts of a legacy completion request, as returned by
+    /// [`num_tokens_from_completion_request`].
+    #[derive(Debug, Default, Clone, PartialEq, Eq)]
+    pub struct CompletionRequestTokens {
+        /// Number of tokens of each prompt, suffix included, in order.
+        pub prompt_tokens: Vec<usize>,
+        /// The context size of the model.
+        pub context_size: usize,
+        /// The `max_tokens` of the request, or the API default of 16.
+        pub max_tokens: usize,
+        /// Indices of the prompts that leave less than `max_tokens` of the context for the
+        /// completion.
+        pub oversized: Vec<usize>,
+    }
+
+    /// Counts the tokens of each prompt of a legacy completion request and checks that each
+    /// leaves room for `max_tokens` in the model's context (see [`get_context_size`]).
+    ///
+    /// String prompts are encoded with the model's tokenizer. Prompts given as token arrays are
+    /// counted by their length. The `suffix`, if any, is counted with every prompt.
     ///
     /// # Arguments
     ///
-    /// * `model` - A string slice representing the name of the model.
-    /// * `messages` - A slice of `async_openai::types::ChatCompletionRequestMessage` instances.
+    /// * `request` - An `async_openai::types::completions::CreateCompletionRequest`.
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// * A `Result` containing the maximum number of tokens (`usize`) allowed for chat completions or an error if the retrieval fails.
-    pub fn get_chat_completion_max_tokens(
-        model: &str,
-        messages: &[ChatCompletionRequestMessage],
-    ) -> Result<usize> {
-        let messages: Vec<super::ChatCompletionRequestMessage> =
-            messages.iter().map(|m| m.into()).collect();
-        super::get_chat_completion_max_tokens(model, &messages)
+    /// Returns an error if no tokenizer or context size is found for the model.
+    pub fn num_tokens_from_completion_request(
+        request: &CreateCompletionRequest,
+    ) -> Result<CompletionRequestTokens> {
+        let model = request.model.as_str();
+        let context_size = get_context_size(model)
+            .ok_or_else(|| anyhow!("Unknown context size for model {}", model))?;
+        let bpe = bpe_for_model(model)?;
+        let suffix_tokens = request
+            .suffix
+            .as_deref()
+            .map_or(0, |suffix| bpe.count_with_special_tokens(suffix));
+        let prompt_tokens: Vec<usize> = match &request.prompt {
+            Prompt::String(prompt) => vec![bpe.count_with_special_tokens(prompt)],
+            Prompt::StringArray(prompts) => prompts
+                .iter()
+                .map(|prompt| bpe.count_with_special_tokens(prompt))
+                .collect(),
+            Prompt::IntegerArray(tokens) => vec![tokens.len()],
+            Prompt::ArrayOfIntegerArray(prompts) => prompts.iter().map(Vec::len).collect(),
+        }
+        .into_iter()
+        .map(|num_tokens| num_tokens + suffix_tokens)
+        .collect();
+        let max_tokens = request
+            .max_tokens
+            .map_or(DEFAULT_COMPLETION_MAX_TOKENS, |n| n as usize);
+        let oversized = prompt_tokens
+            .iter()
+            .enumerate()
+            .filter(|&(_, &num_tokens)| num_tokens + max_tokens > context_size)
+            .map(|(index, _)| index)
+            .collect();
+        Ok(CompletionRequestTokens {
+            prompt_tokens,
+            context_size,
+            max_tokens,
+            oversized,
+        })
     }
 
     #[cfg(test)]
@@ -785,8 +3283,9 @@ pub mod async_openai {
     mod tests {
         use super::*;
         use async_openai::types::chat::{
-            ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessage,
-            ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
+            ChatCompletionMessageToolCall, ChatCompletionNamedToolChoice,
+            ChatCompletionRequestAssistantMessage, ChatCompletionRequestSystemMessage,
+            ChatCompletionRequestUserMessage, ChatCompletionTool, FunctionName,
         };
 
         #[test]
@@ -893,6 +3392,164 @@ pub mod async_openai {
             );
         }
 
+        #[test]
+        fn test_num_tokens_from_messages_with_images() {
+            use async_openai::types::chat::{
+                ChatCompletionRequestMessageContentPartImage,
+                ChatCompletionRequestMessageContentPartText,
+            };
+
+            let image = |url: &str, detail| {
+                ChatCompletionRequestUserMessageContentPart::ImageUrl(
+                    ChatCompletionRequestMessageContentPartImage {
+                        image_url: ImageUrl {
+                            url: url.to_string(),
+                            detail,
+                        },
+                    },
+                )
+            };
+            let messages = &[ChatCompletionRequestMessage::User(
+                ChatCompletionRequestUserMessage {
+                    content: ChatCompletionRequestUserMessageContent::Array(vec![
+                        ChatCompletionRequestUserMessageContentPart::Text(
+                            ChatCompletionRequestMessageContentPartText {
+                                text: "What is in these images?".to_string(),
+                            },
+                        ),
+                        image("https://example.com/a.png", Some(ImageDetail::High)),
+                        image("https://example.com/b.png", Some(ImageDetail::Low)),
+                        image("https://example.com/unknown.png", None),
+                    ]),
+                    name: None,
+                },
+            )];
+            let text_only = num_tokens_from_messages("gpt-4o", messages).unwrap();
+            let with_images = num_tokens_from_messages_with_images("gpt-4o", messages, |image| {
+                match image.url.as_str() {
+                    "https://example.com/a.png" | "https://example.com/b.png" => Some((1024, 1024)),
+                    _ => None,
+                }
+            })
+            .unwrap();
+            assert_eq!(with_images, text_only + 765 + 85);
+        }
+
+        #[test]
+        fn test_num_tokens_breakdown_from_messages() {
+            let messages = &[ChatCompletionRequestMessage::System(
+                ChatCompletionRequestSystemMessage {
+                    content: ChatCompletionRequestSystemMessageContent::Text(
+                        "You are a helpful assistant.".to_string(),
+                    ),
+                    name: None,
+                },
+            )];
+            let breakdown = num_tokens_breakdown_from_messages("gpt-4o", messages).unwrap();
+            assert_eq!(breakdown.messages.len(), 1);
+            assert_eq!(
+                breakdown.total(),
+                num_tokens_from_messages("gpt-4o", messages).unwrap()
+            );
+        }
+
+        #[test]
+        fn test_num_tokens_from_messages_with_media() {
+            use async_openai::types::chat::{
+                ChatCompletionRequestMessageContentPartAudio,
+                ChatCompletionRequestMessageContentPartFile, InputAudio,
+            };
+            use std::time::Duration;
+
+            struct Resolver;
+
+            impl MediaResolver for Resolver {
+                fn audio_duration(&self, audio: &InputAudio) -> Option<Duration> {
+                    (audio.data == "clip").then(|| Duration::from_secs(5))
+                }
+
+                fn file_input(&self, _file: &FileObject) -> Option<FileInput> {
+                    Some(FileInput::new("Page one", 1))
+                }
+            }
+
+            let file: FileObject =
+                serde_json::from_value(serde_json::json!({ "file_id": "file-abc" })).unwrap();
+            let messages = &[ChatCompletionRequestMessage::User(
+                ChatCompletionRequestUserMessage {
+                    content: ChatCompletionRequestUserMessageContent::Array(vec![
+                        ChatCompletionRequestUserMessageContentPart::InputAudio(
+                            ChatCompletionRequestMessageContentPartAudio {
+                                input_audio: InputAudio {
+                                    data: "clip".to_string(),
+                                    ..Default::default()
+                                },
+                            },
+                        ),
+                        ChatCompletionRequestUserMessageContentPart::File(
+                            ChatCompletionRequestMessageContentPartFile { file },
+                        ),
+                    ]),
+                    name: None,
+                },
+            )];
+            let model = "gpt-4o-audio-preview";
+            let text_only = num_tokens_from_messages(model, messages).unwrap();
+            let file_tokens =
+                crate::multimodal::num_tokens_from_file(model, &FileInput::new("Page one", 1))
+                    .unwrap();
+            assert_eq!(
+                num_tokens_from_messages_with_media(model, messages, &Resolver).unwrap(),
+                text_only + 50 + file_tokens
+            );
+            // Audio is rejected by models without audio input
+            assert!(num_tokens_from_messages_with_media("gpt-4o", messages, &Resolver).is_err());
+        }
+
+        #[test]
+        fn test_num_tokens_from_response_request() {
+            let request: CreateResponse = serde_json::from_value(serde_json::json!({
+                "model": "gpt-4o",
+                "instructions": "Be brief.",
+                "input": [
+                    { "role": "user", "content": "Weather in Paris?" },
+                    {
+                        "type": "reasoning",
+                        "id": "rs_1",
+                        "summary": [{ "type": "summary_text", "text": "Use the tool." }]
+                    },
+                    {
+                        "type": "function_call",
+                        "call_id": "call_1",
+                        "name": "get_weather",
+                        "arguments": "{\"city\":\"Paris\"}"
+                    },
+                    { "type": "function_call_output", "call_id": "call_1", "output": "18C" },
+                    { "type": "item_reference", "id": "msg_0" }
+                ],
+                "tools": [{
+                    "type": "function",
+                    "name": "get_weather",
+                    "parameters": {
+                        "type": "object",
+                        "properties": { "city": { "type": "string" } },
+                        "required": ["city"]
+                    }
+                }],
+                "tool_choice": "required"
+            }))
+            .unwrap();
+            let native = ResponseRequest::from(&request);
+            assert_eq!(native.instructions.as_deref(), Some("Be brief."));
+            assert_eq!(native.input.len(), 4);
+            assert_eq!(native.tools.len(), 1);
+            assert_eq!(native.tool_choice, Some(super::super::ToolChoice::Required));
+            assert_eq!(
+                num_tokens_from_response_request("gpt-4o", &request).unwrap(),
+                crate::responses::num_tokens_from_response_request("gpt-4o", &native).unwrap()
+            );
+        }
+
         #[test]
         fn test_get_chat_completion_max_tokens() {
             let model = "gpt-4o";
@@ -907,5 +3564,163 @@ pub mod async_openai {
             let max_tokens = get_chat_completion_max_tokens(model, messages).unwrap();
             assert!(max_tokens > 0);
         }
+
+        #[test]
+        fn test_num_tokens_from_embedding_request() {
+            let long = "hello ".repeat(9000);
+            let request = CreateEmbeddingRequest {
+                model: "text-embedding-3-small".to_string(),
+                input: EmbeddingInput::StringArray(vec!["hello world".to_string(), long]),
+                ..Default::default()
+            };
+            let tokens = num_tokens_from_embedding_request(&request).unwrap();
+            assert_eq!(tokens.input_tokens[0], 2);
+            assert_eq!(
+                tokens.total_tokens,
+                tokens.input_tokens.iter().sum::<usize>()
+            );
+            assert_eq!(tokens.oversized.len(), 1);
+            assert_eq!(tokens.oversized[0].index, 1);
+            assert!(!tokens.is_within_limits());
+
+            let request = CreateEmbeddingRequest {
+                model: "text-embedding-3-small".to_string(),
+                input: EmbeddingInput::ArrayOfIntegerArray(vec![vec![1, 2, 3]; 2049]),
+                ..Default::default()
+            };
+            let tokens = num_tokens_from_embedding_request(&request).unwrap();
+            assert_eq!(tokens.total_tokens, 3 * 2049);
+            assert!(tokens.oversized.is_empty());
+            assert!(tokens.exceeds_max_inputs);
+            assert!(!tokens.exceeds_max_tokens);
+
+            let request = CreateEmbeddingRequest {
+                model: "gpt-4o".to_string(),
+                ..Default::default()
+            };
+            assert!(num_tokens_from_embedding_request(&request).is_err());
+        }
+
+        #[test]
+        fn test_num_tokens_from_completion_request() {
+            let request = CreateCompletionRequest {
+                model: "gpt-3.5-turbo-instruct".to_string(),
+                prompt: Prompt::StringArray(vec![
+                    "Once upon a time".to_string(),
+                    "word ".repeat(17_000),
+                ]),
+                suffix: Some(" The end.".to_string()),
+                max_tokens: Some(200),
+                ..Default::default()
+            };
+            let tokens = num_tokens_from_completion_request(&request).unwrap();
+            let bpe = bpe_for_model("gpt-3.5-turbo-instruct").unwrap();
+            assert_eq!(
+                tokens.prompt_tokens[0],
+                bpe.count_with_special_tokens("Once upon a time")
+                    + bpe.count_with_special_tokens(" The end.")
+            );
+            assert_eq!(tokens.max_tokens, 200);
+            assert_eq!(tokens.oversized, vec![1]);
+
+            let request = CreateCompletionRequest {
+                model: "davinci-002".to_string(),
+                prompt: Prompt::IntegerArray(vec![1; 10]),
+                ..Default::default()
+            };
+            let tokens = num_tokens_from_completion_request(&request).unwrap();
+            assert_eq!(tokens.prompt_tokens, vec![10]);
+            assert_eq!(tokens.max_tokens, 16);
+            assert!(tokens.oversized.is_empty());
+        }
+
+        #[test]
+        fn test_check_chat_completion_request() {
+            let user = ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+                content: ChatCompletionRequestUserMessageContent::Text(
+                    "What's the weather in Paris?".to_string(),
+                ),
+                name: None,
+            });
+            let request = CreateChatCompletionRequest {
+                model: "gpt-4o".to_string(),
+                messages: vec![user.clone()],
+                tools: Some(vec![ChatCompletionTools::Function(ChatCompletionTool {
+                    function: FunctionObject {
+                        name: "get_weather".to_string(),
+                        description: Some("Get the weather".to_string()),
+                        parameters: Some(serde_json::json!({
+                            "type": "object",
+                            "properties": { "city": { "type": "string" } },
+                        })),
+                        strict: None,
+                    },
+                })]),
+                tool_choice: Some(ChatCompletionToolChoiceOption::Function(
+                    ChatCompletionNamedToolChoice {
+                        function: FunctionName {
+                            name: "get_weather".to_string(),
+                        },
+                    },
+                )),
+                max_completion_tokens: Some(500),
+                ..Default::default()
+            };
+            let converted: super::super::ChatCompletionRequest = (&request).into();
+            assert_eq!(converted.tools.len(), 1);
+            assert_eq!(
+                converted.tool_choice,
+                Some(super::super::ToolChoice::Function(
+                    "get_weather".to_string()
+                ))
+            );
+
+            let report = check_chat_completion_request(&request).unwrap();
+            assert_eq!(
+                report.prompt_tokens,
+                super::super::num_tokens_from_request("gpt-4o", &converted).unwrap()
+            );
+            assert!(report.prompt_tokens > num_tokens_from_messages("gpt-4o", &[user]).unwrap());
+            assert_eq!(report.context_size, 128_000);
+            assert_eq!(
+                Some(report.available_completion_tokens),
+                get_max_output_tokens("gpt-4o")
+            );
+            assert_eq!(report.requested_completion_tokens, Some(500));
+
+            let too_long = CreateChatCompletionRequest {
+                max_completion_tokens: Some(1_000_000),
+                ..request.clone()
+            };
+            let err = check_chat_completion_request(&too_long).unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<ChatRequestError>(),
+                Some(&ChatRequestError::CompletionTooLong {
+                    requested_completion_tokens: 1_000_000,
+                    available_completion_tokens: report.available_completion_tokens,
+                })
+            );
+
+            let huge = CreateChatCompletionRequest {
+                model: "gpt-4".to_string(),
+                messages: vec![ChatCompletionRequestMessage::User(
+                    ChatCompletionRequestUserMessage {
+                        content: ChatCompletionRequestUserMessageContent::Text(
+                            "word ".repeat(9_000),
+                        ),
+                        name: None,
+                    },
+                )],
+                ..Default::default()
+            };
+            let err = check_chat_completion_request(&huge).unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<ChatRequestError>(),
+                Some(ChatRequestError::PromptTooLong {
+                    context_size: 8192,
+                    ..
+                })
+            ));
+        }
     }
 }
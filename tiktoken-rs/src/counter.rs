@@ -0,0 +1,216 @@
+/*!
+ * abstracts the tokenizer used to count chat requests.
+ *
+ * A [`ChatCounter`](crate::ChatCounter) (and a
+ * [`ConversationCounter`](crate::history::ConversationCounter)) can encode with any
+ * [`TokenCounter`], so a cache, a remote tokenizer or a tokenizer for another provider's
+ * models can be used instead of the model's [`CoreBPE`].
+ *
+ * # Example
+ *
+ * ```
+ * use std::collections::HashSet;
+ *
+ * use anyhow::Result;
+ * use tiktoken_rs::counter::TokenCounter;
+ * use tiktoken_rs::{
+ *     num_tokens_from_messages, o200k_base_singleton, ChatCompletionRequestMessage, ChatCounter,
+ *     Rank,
+ * };
+ *
+ * /// Counts one token per whitespace-separated word.
+ * struct WordCounter;
+ *
+ * impl TokenCounter for WordCounter {
+ *     fn encode_ordinary(&self, text: &str) -> Vec<Rank> {
+ *         text.split_whitespace().map(|_| 0).collect()
+ *     }
+ *
+ *     fn encode_with_special_tokens(&self, text: &str) -> Vec<Rank> {
+ *         self.encode_ordinary(text)
+ *     }
+ *
+ *     fn decode(&self, _tokens: &[Rank]) -> Result<String> {
+ *         anyhow::bail!("WordCounter cannot decode")
+ *     }
+ *
+ *     fn special_tokens(&self) -> HashSet<&str> {
+ *         HashSet::new()
+ *     }
+ * }
+ *
+ * let messages = vec![ChatCompletionRequestMessage {
+ *     role: "user".to_string(),
+ *     content: Some("Hello there, how are you?".into()),
+ *     ..Default::default()
+ * }];
+ * let counter = ChatCounter::with_counter("gpt-4o", &WordCounter).unwrap();
+ * let words = counter.num_tokens(&messages).unwrap();
+ * assert_eq!(words, 1 + 5 + 3 + 3);
+ *
+ * // CoreBPE is a TokenCounter too
+ * let bpe = o200k_base_singleton();
+ * assert_eq!(
+ *     ChatCounter::with_counter("gpt-4o", bpe).unwrap().num_tokens(&messages).unwrap(),
+ *     num_tokens_from_messages("gpt-4o", &messages).unwrap()
+ * );
+ * ```
+ */
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use crate::{CoreBPE, Rank};
+
+/// A tokenizer that can count, encode and decode text.
+///
+/// Only encoding, decoding and the list of special tokens are required; the counts default
+/// to the length of the encoding. Implementations that can count without encoding, e.g. a
+/// cache, should override [`TokenCounter::count_with_special_tokens`], which is what chat
+/// counting uses.
+pub trait TokenCounter {
+    /// Encodes text, treating special tokens in it as plain text.
+    fn encode_ordinary(&self, text: &str) -> Vec<Rank>;
+
+    /// Encodes text, treating special tokens in it, e.g. `<|endoftext|>`, as special tokens.
+    fn encode_with_special_tokens(&self, text: &str) -> Vec<Rank>;
+
+    /// Decodes tokens into text.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a token is unknown or the text cannot be decoded.
+    fn decode(&self, tokens: &[Rank]) -> Result<String>;
+
+    /// Returns the special tokens of the tokenizer.
+    fn special_tokens(&self) -> HashSet<&str>;
+
+    /// Counts the tokens of text, treating special tokens in it as plain text.
+    fn count_ordinary(&self, text: &str) -> usize {
+        self.encode_ordinary(text).len()
+    }
+
+    /// Counts the tokens of text, treating special tokens in it as special tokens.
+    fn count_with_special_tokens(&self, text: &str) -> usize {
+        self.encode_with_special_tokens(text).len()
+    }
+}
+
+impl TokenCounter for CoreBPE {
+    fn encode_ordinary(&self, text: &str) -> Vec<Rank> {
+        CoreBPE::encode_ordinary(self, text)
+    }
+
+    fn encode_with_special_tokens(&self, text: &str) -> Vec<Rank> {
+        CoreBPE::encode_with_special_tokens(self, text)
+    }
+
+    fn decode(&self, tokens: &[Rank]) -> Result<String> {
+        CoreBPE::decode(self, tokens)
+    }
+
+    fn special_tokens(&self) -> HashSet<&str> {
+        CoreBPE::special_tokens(self)
+    }
+
+    fn count_ordinary(&self, text: &str) -> usize {
+        CoreBPE::count_ordinary(self, text)
+    }
+
+    fn count_with_special_tokens(&self, text: &str) -> usize {
+        CoreBPE::count_with_special_tokens(self, text)
+    }
+}
+
+impl<T: TokenCounter + ?Sized> TokenCounter for &T {
+    fn encode_ordinary(&self, text: &str) -> Vec<Rank> {
+        (**self).encode_ordinary(text)
+    }
+
+    fn encode_with_special_tokens(&self, text: &str) -> Vec<Rank> {
+        (**self).encode_with_special_tokens(text)
+    }
+
+    fn decode(&self, tokens: &[Rank]) -> Result<String> {
+        (**self).decode(tokens)
+    }
+
+    fn special_tokens(&self) -> HashSet<&str> {
+        (**self).special_tokens()
+    }
+
+    fn count_ordinary(&self, text: &str) -> usize {
+        (**self).count_ordinary(text)
+    }
+
+    fn count_with_special_tokens(&self, text: &str) -> usize {
+        (**self).count_with_special_tokens(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::o200k_base_singleton;
+
+    /// Encodes each byte as a token, and `<|eot|>` as a single special token.
+    struct ByteCounter;
+
+    impl TokenCounter for ByteCounter {
+        fn encode_ordinary(&self, text: &str) -> Vec<Rank> {
+            text.bytes().map(Rank::from).collect()
+        }
+
+        fn encode_with_special_tokens(&self, text: &str) -> Vec<Rank> {
+            let mut tokens = Vec::new();
+            for (i, part) in text.split("<|eot|>").enumerate() {
+                if i > 0 {
+                    tokens.push(256);
+                }
+                tokens.extend(self.encode_ordinary(part));
+            }
+            tokens
+        }
+
+        fn decode(&self, tokens: &[Rank]) -> Result<String> {
+            let bytes = tokens.iter().map(|&t| t as u8).collect();
+            Ok(String::from_utf8(bytes)?)
+        }
+
+        fn special_tokens(&self) -> HashSet<&str> {
+            HashSet::from(["<|eot|>"])
+        }
+    }
+
+    fn counts<C: TokenCounter>(counter: C, text: &str) -> (usize, usize) {
+        (
+            counter.count_ordinary(text),
+            counter.count_with_special_tokens(text),
+        )
+    }
+
+    #[test]
+    fn test_default_counts() {
+        assert_eq!(counts(ByteCounter, "hi<|eot|>"), (9, 3));
+        assert_eq!(counts(ByteCounter, ""), (0, 0));
+    }
+
+    #[test]
+    fn test_reference_impl() {
+        let text = "Hello<|endoftext|>";
+        let bpe = o200k_base_singleton();
+        assert_eq!(counts(&ByteCounter, text), counts(ByteCounter, text));
+        assert_eq!(counts(bpe, text), (bpe.encode_ordinary(text).len(), 2));
+        let by_ref: &dyn TokenCounter = &bpe;
+        assert_eq!(
+            by_ref.encode_with_special_tokens(text),
+            bpe.encode_with_special_tokens(text)
+        );
+        assert_eq!(by_ref.decode(&bpe.encode_ordinary(text)).unwrap(), text);
+        assert!(by_ref.special_tokens().contains("<|endoftext|>"));
+        let by_ref: &dyn TokenCounter = &&ByteCounter;
+        assert_eq!(by_ref.decode(&[104, 105]).unwrap(), "hi");
+        assert_eq!(by_ref.special_tokens(), ByteCounter.special_tokens());
+    }
+}
@@ -0,0 +1,676 @@
+/*!
+ * renders and parses conversations in the Harmony response format used by gpt-oss models.
+ *
+ * Each message is a header and a body delimited by special tokens of `o200k_harmony`:
+ *
+ * ```text
+ * <|start|>user<|message|>What is the weather in Tokyo?<|end|>
+ * <|start|>assistant<|channel|>analysis<|message|>Need to use the tool.<|end|>
+ * <|start|>assistant<|channel|>commentary to=functions.get_weather <|constrain|>json<|message|>{"city":"Tokyo"}<|call|>
+ * <|start|>functions.get_weather to=assistant<|channel|>commentary<|message|>{"temp":20}<|end|>
+ * <|start|>assistant<|channel|>final<|message|>It is 20C in Tokyo.<|return|>
+ * ```
+ *
+ * See <https://cookbook.openai.com/articles/openai-harmony> for the format. The content of
+ * system and developer messages (model identity, reasoning effort, tool namespaces) is
+ * plain text and is left to the caller.
+ */
+
+use anyhow::{anyhow, Result};
+
+use crate::{o200k_harmony_singleton, Rank};
+
+/// Ends the message that completes the assistant's turn, when generated by the model.
+pub const RETURN: Rank = 200002;
+/// Precedes the content type of a message, e.g. `json`.
+pub const CONSTRAIN: Rank = 200003;
+/// Precedes the channel of a message.
+pub const CHANNEL: Rank = 200005;
+/// Starts a message, followed by its role.
+pub const START: Rank = 200006;
+/// Ends a message.
+pub const END: Rank = 200007;
+/// Separates the header of a message from its content.
+pub const MESSAGE: Rank = 200008;
+/// Ends a message that calls a tool.
+pub const CALL: Rank = 200012;
+
+/// A message in the Harmony format.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HarmonyMessage {
+    /// The author of the message: `system`, `developer`, `user`, `assistant`, or the name of
+    /// the tool that produced it, e.g. `functions.get_weather`.
+    pub role: String,
+    /// The channel of the message, e.g. `analysis`, `commentary` or `final`.
+    pub channel: Option<String>,
+    /// Who the message is addressed to, e.g. `functions.get_weather` for a tool call, or
+    /// `assistant` for a tool result.
+    pub recipient: Option<String>,
+    /// The type of the content, e.g. `json` for tool call arguments.
+    pub content_type: Option<String>,
+    /// The content of the message.
+    pub content: String,
+}
+
+impl HarmonyMessage {
+    /// Creates a message with the given role and content, without channel or recipient.
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns `true` if the message is an assistant message addressed to a tool.
+    pub fn is_tool_call(&self) -> bool {
+        self.role == "assistant" && self.recipient.is_some()
+    }
+}
+
+/// Renders a single message.
+///
+/// Tool calls end with `<|call|>` and all other messages with `<|end|>`, as in a prompt: the
+/// `<|return|>` the model generates after its final message is replaced by `<|end|>` when the
+/// message is sent back as history.
+///
+/// The recipient of an assistant message is rendered after its channel, and the recipient of
+/// other messages after their role, as in the examples of the format.
+pub fn render_message(message: &HarmonyMessage) -> Vec<Rank> {
+    let bpe = o200k_harmony_singleton();
+    let mut tokens = vec![START];
+    tokens.extend(bpe.encode_ordinary(&message.role));
+    let recipient = message
+        .recipient
+        .as_ref()
+        .map(|recipient| format!(" to={}", recipient));
+    let (role_recipient, channel_recipient) = match &message.channel {
+        Some(_) if message.role == "assistant" => (None, recipient),
+        _ => (recipient, None),
+    };
+    if let Some(recipient) = role_recipient {
+        tokens.extend(bpe.encode_ordinary(&recipient));
+    }
+    if let Some(channel) = &message.channel {
+        tokens.push(CHANNEL);
+        let header = format!("{}{}", channel, channel_recipient.unwrap_or_default());
+        tokens.extend(bpe.encode_ordinary(&header));
+    }
+    if let Some(content_type) = &message.content_type {
+        tokens.extend(bpe.encode_ordinary(" "));
+        tokens.push(CONSTRAIN);
+        tokens.extend(bpe.encode_ordinary(content_type));
+    }
+    tokens.push(MESSAGE);
+    tokens.extend(bpe.encode_ordinary(&message.content));
+    tokens.push(if message.is_tool_call() { CALL } else { END });
+    tokens
+}
+
+/// Renders a conversation.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::harmony::{render_conversation, HarmonyMessage};
+/// use tiktoken_rs::o200k_harmony_singleton;
+///
+/// let tokens = render_conversation(&[HarmonyMessage::new("user", "Hi")]);
+/// let text = o200k_harmony_singleton().decode(&tokens).unwrap();
+/// assert_eq!(text, "<|start|>user<|message|>Hi<|end|>");
+/// ```
+pub fn render_conversation(messages: &[HarmonyMessage]) -> Vec<Rank> {
+    messages.iter().flat_map(render_message).collect()
+}
+
+/// Renders a conversation followed by `<|start|>assistant`, the prompt to sample the
+/// assistant's next message from.
+///
+/// The completion can be parsed with [`parse_completion`].
+pub fn render_conversation_for_completion(messages: &[HarmonyMessage]) -> Vec<Rank> {
+    let mut tokens = render_conversation(messages);
+    tokens.push(START);
+    tokens.extend(o200k_harmony_singleton().encode_ordinary("assistant"));
+    tokens
+}
+
+/// Parses rendered messages, each starting with `<|start|>`.
+///
+/// # Errors
+///
+/// Returns an error if the tokens are not well-formed Harmony messages, including when the
+/// last message is not terminated.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::harmony::{parse_messages, render_conversation, HarmonyMessage};
+///
+/// let messages = vec![
+///     HarmonyMessage::new("user", "Hi"),
+///     HarmonyMessage {
+///         channel: Some("final".to_string()),
+///         ..HarmonyMessage::new("assistant", "Hello!")
+///     },
+/// ];
+/// assert_eq!(parse_messages(&render_conversation(&messages)).unwrap(), messages);
+/// ```
+pub fn parse_messages(tokens: &[Rank]) -> Result<Vec<HarmonyMessage>> {
+    parse(tokens, None)
+}
+
+/// Parses the tokens sampled after a prompt from [`render_conversation_for_completion`].
+///
+/// The first message continues the `<|start|>assistant` header of the prompt; later messages
+/// start with their own `<|start|>`.
+///
+/// # Errors
+///
+/// Returns an error if the tokens are not well-formed Harmony messages, including when the
+/// last message is not terminated, e.g. because generation hit a length limit.
+pub fn parse_completion(tokens: &[Rank]) -> Result<Vec<HarmonyMessage>> {
+    parse(tokens, Some("assistant"))
+}
+
+fn parse(tokens: &[Rank], mut role: Option<&str>) -> Result<Vec<HarmonyMessage>> {
+    let bpe = o200k_harmony_singleton();
+    let mut messages = Vec::new();
+    let mut rest = tokens;
+    while !rest.is_empty() {
+        let mut header = match role.take() {
+            Some(role) => role.to_string(),
+            None => {
+                if rest[0] != START {
+                    anyhow::bail!("Expected <|start|>, found token {}", rest[0])
+                }
+                rest = &rest[1..];
+                String::new()
+            }
+        };
+        let header_len = rest
+            .iter()
+            .position(|&token| token == MESSAGE)
+            .ok_or_else(|| anyhow!("Missing <|message|> after message header"))?;
+        header.push_str(&bpe.decode(&rest[..header_len])?);
+        rest = &rest[header_len + 1..];
+        let content_len = rest
+            .iter()
+            .position(|&token| matches!(token, END | RETURN | CALL))
+            .ok_or_else(|| anyhow!("Missing end of message"))?;
+        let content = bpe.decode(&rest[..content_len])?;
+        rest = &rest[content_len + 1..];
+        messages.push(parse_header(&header, content)?);
+    }
+    Ok(messages)
+}
+
+/// Parses the decoded header of a message, e.g.
+/// `assistant<|channel|>commentary to=functions.get_weather <|constrain|>json`.
+pub(crate) fn parse_header(header: &str, content: String) -> Result<HarmonyMessage> {
+    let (header, content_type) = match header.split_once("<|constrain|>") {
+        Some((header, content_type)) => (header, Some(content_type.trim().to_string())),
+        None => (header, None),
+    };
+    let (role_part, channel_part) = match header.split_once("<|channel|>") {
+        Some((role, channel)) => (role, Some(channel)),
+        None => (header, None),
+    };
+    let mut message = HarmonyMessage {
+        content_type,
+        content,
+        ..Default::default()
+    };
+    for (i, word) in role_part.split_whitespace().enumerate() {
+        match word.strip_prefix("to=") {
+            Some(recipient) => message.recipient = Some(recipient.to_string()),
+            None if i == 0 => message.role = word.to_string(),
+            None => anyhow::bail!("Unexpected {:?} in message header", word),
+        }
+    }
+    if message.role.is_empty() {
+        anyhow::bail!("Missing role in message header")
+    }
+    if let Some(channel_part) = channel_part {
+        for (i, word) in channel_part.split_whitespace().enumerate() {
+            match word.strip_prefix("to=") {
+                Some(recipient) => message.recipient = Some(recipient.to_string()),
+                None if i == 0 => message.channel = Some(word.to_string()),
+                // The content type may also follow the channel without <|constrain|>
+                None if message.content_type.is_none() => {
+                    message.content_type = Some(word.to_string())
+                }
+                None => anyhow::bail!("Unexpected {:?} in message header", word),
+            }
+        }
+    }
+    Ok(message)
+}
+
+/// An event emitted by [`StreamParser`] as tokens arrive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HarmonyEvent {
+    /// The header of a message is complete. The message has no content yet.
+    MessageStart(HarmonyMessage),
+    /// Text was appended to the content of the current message.
+    ContentDelta(String),
+    /// A message addressed to a tool ended with `<|call|>`. Its content holds the arguments.
+    ToolCall(HarmonyMessage),
+    /// A message ended with `<|end|>` or `<|return|>`.
+    MessageEnd(HarmonyMessage),
+}
+
+#[derive(Debug, Clone)]
+enum StreamState {
+    /// Waiting for `<|start|>`.
+    Start,
+    /// Reading the header of a message, up to `<|message|>`.
+    Header { prefix: String, tokens: Vec<Rank> },
+    /// Reading the content of a message, up to its end token.
+    Content {
+        message: HarmonyMessage,
+        pending: Vec<u8>,
+    },
+}
+
+/// An incremental parser for Harmony tokens, fed one token at a time from a generation stream.
+///
+/// Content deltas are emitted as soon as they decode to complete UTF-8 characters, so
+/// analysis and final channel text can be told apart while the model is still generating.
+/// Invalid UTF-8 in the content, which a model can sample, is replaced with U+FFFD.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::harmony::{HarmonyEvent, StreamParser};
+/// use tiktoken_rs::o200k_harmony_singleton;
+///
+/// let tokens = o200k_harmony_singleton().encode_with_special_tokens(
+///     "<|channel|>analysis<|message|>Think.<|end|><|start|>assistant<|channel|>final<|message|>Hi!<|return|>",
+/// );
+/// let mut parser = StreamParser::for_completion();
+/// let mut final_text = String::new();
+/// for token in tokens {
+///     for event in parser.push(token).unwrap() {
+///         if let HarmonyEvent::ContentDelta(delta) = event {
+///             if parser.current().and_then(|m| m.channel.as_deref()) == Some("final") {
+///                 final_text.push_str(&delta);
+///             }
+///         }
+///     }
+/// }
+/// assert_eq!(final_text, "Hi!");
+/// assert!(parser.is_done());
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamParser {
+    state: StreamState,
+    messages: Vec<HarmonyMessage>,
+    done: bool,
+}
+
+impl Default for StreamParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamParser {
+    /// Creates a parser for a stream of whole messages, each starting with `<|start|>`.
+    pub fn new() -> Self {
+        Self {
+            state: StreamState::Start,
+            messages: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Creates a parser for the tokens sampled after a prompt from
+    /// [`render_conversation_for_completion`], whose first message continues the
+    /// `<|start|>assistant` header of the prompt.
+    pub fn for_completion() -> Self {
+        Self {
+            state: StreamState::Header {
+                prefix: "assistant".to_string(),
+                tokens: Vec::new(),
+            },
+            ..Self::new()
+        }
+    }
+
+    /// Returns the message being read, once its header is complete. Its content holds the
+    /// text received so far.
+    pub fn current(&self) -> Option<&HarmonyMessage> {
+        match &self.state {
+            StreamState::Content { message, .. } => Some(message),
+            _ => None,
+        }
+    }
+
+    /// Returns the messages completed so far.
+    pub fn messages(&self) -> &[HarmonyMessage] {
+        &self.messages
+    }
+
+    /// Returns `true` once the model has ended its turn with `<|return|>`.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Feeds the next token to the parser, and returns the events it completes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token is unknown or out of place, e.g. content before
+    /// `<|start|>`. The parser state is left unchanged.
+    pub fn push(&mut self, token: Rank) -> Result<Vec<HarmonyEvent>> {
+        let bpe = o200k_harmony_singleton();
+        let mut events = Vec::new();
+        match &mut self.state {
+            StreamState::Start => {
+                if token != START {
+                    anyhow::bail!("Expected <|start|>, found token {}", token)
+                }
+                self.state = StreamState::Header {
+                    prefix: String::new(),
+                    tokens: Vec::new(),
+                };
+            }
+            StreamState::Header { prefix, tokens } => match token {
+                MESSAGE => {
+                    let header = format!("{}{}", prefix, bpe.decode(tokens)?);
+                    let message = parse_header(&header, String::new())?;
+                    events.push(HarmonyEvent::MessageStart(message.clone()));
+                    self.state = StreamState::Content {
+                        message,
+                        pending: Vec::new(),
+                    };
+                }
+                START | END | RETURN | CALL => {
+                    anyhow::bail!("Unexpected token {} in message header", token)
+                }
+                _ => {
+                    bpe.decode_bytes(&[token])?;
+                    tokens.push(token);
+                }
+            },
+            StreamState::Content { message, pending } => match token {
+                END | RETURN | CALL => {
+                    if !pending.is_empty() {
+                        // The message ended inside a character
+                        let delta = String::from_utf8_lossy(pending).into_owned();
+                        message.content.push_str(&delta);
+                        events.push(HarmonyEvent::ContentDelta(delta));
+                    }
+                    let message = std::mem::take(message);
+                    self.messages.push(message.clone());
+                    events.push(if token == CALL {
+                        HarmonyEvent::ToolCall(message)
+                    } else {
+                        HarmonyEvent::MessageEnd(message)
+                    });
+                    self.done = token == RETURN;
+                    self.state = StreamState::Start;
+                }
+                START | MESSAGE | CHANNEL | CONSTRAIN => {
+                    anyhow::bail!("Unexpected token {} in message content", token)
+                }
+                _ => {
+                    let decoded = bpe.decode_bytes(&[token])?;
+                    let mut bytes = std::mem::take(pending);
+                    bytes.extend(decoded);
+                    let (delta, incomplete) = decode_utf8_prefix(&bytes);
+                    *pending = incomplete.to_vec();
+                    if !delta.is_empty() {
+                        message.content.push_str(&delta);
+                        events.push(HarmonyEvent::ContentDelta(delta));
+                    }
+                }
+            },
+        }
+        Ok(events)
+    }
+}
+
+/// Decodes `bytes` up to a trailing incomplete character, which is returned separately.
+/// Invalid sequences are replaced with U+FFFD.
+fn decode_utf8_prefix(mut bytes: &[u8]) -> (String, &[u8]) {
+    let mut text = String::new();
+    loop {
+        match std::str::from_utf8(bytes) {
+            Ok(valid) => {
+                text.push_str(valid);
+                return (text, &[]);
+            }
+            Err(e) => {
+                let (valid, rest) = bytes.split_at(e.valid_up_to());
+                // `valid` is valid UTF-8, so this borrows without replacing anything
+                text.push_str(&String::from_utf8_lossy(valid));
+                match e.error_len() {
+                    Some(len) => {
+                        text.push(char::REPLACEMENT_CHARACTER);
+                        bytes = &rest[len..];
+                    }
+                    None => return (text, rest),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(tokens: &[Rank]) -> String {
+        o200k_harmony_singleton().decode(tokens).unwrap()
+    }
+
+    fn tool_call() -> HarmonyMessage {
+        HarmonyMessage {
+            channel: Some("commentary".to_string()),
+            recipient: Some("functions.get_weather".to_string()),
+            content_type: Some("json".to_string()),
+            ..HarmonyMessage::new("assistant", r#"{"city":"Tokyo"}"#)
+        }
+    }
+
+    fn tool_result() -> HarmonyMessage {
+        HarmonyMessage {
+            channel: Some("commentary".to_string()),
+            recipient: Some("assistant".to_string()),
+            ..HarmonyMessage::new("functions.get_weather", r#"{"temp":20}"#)
+        }
+    }
+
+    #[test]
+    fn test_special_tokens() {
+        let bpe = o200k_harmony_singleton();
+        for (token, text) in [
+            (RETURN, "<|return|>"),
+            (CONSTRAIN, "<|constrain|>"),
+            (CHANNEL, "<|channel|>"),
+            (START, "<|start|>"),
+            (END, "<|end|>"),
+            (MESSAGE, "<|message|>"),
+            (CALL, "<|call|>"),
+        ] {
+            assert_eq!(bpe.encode_with_special_tokens(text), vec![token]);
+        }
+    }
+
+    #[test]
+    fn test_render_tool_call_and_result() {
+        assert_eq!(
+            decode(&render_message(&tool_call())),
+            "<|start|>assistant<|channel|>commentary to=functions.get_weather <|constrain|>json<|message|>{\"city\":\"Tokyo\"}<|call|>"
+        );
+        assert_eq!(
+            decode(&render_message(&tool_result())),
+            "<|start|>functions.get_weather to=assistant<|channel|>commentary<|message|>{\"temp\":20}<|end|>"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let messages = vec![
+            HarmonyMessage::new("system", "You are ChatGPT.\nReasoning: high"),
+            HarmonyMessage::new("user", "What is the weather in Tokyo?"),
+            HarmonyMessage {
+                channel: Some("analysis".to_string()),
+                ..HarmonyMessage::new("assistant", "Need to use the tool.")
+            },
+            tool_call(),
+            tool_result(),
+        ];
+        let tokens = render_conversation(&messages);
+        assert_eq!(parse_messages(&tokens).unwrap(), messages);
+    }
+
+    #[test]
+    fn test_parse_completion() {
+        let prompt = render_conversation_for_completion(&[HarmonyMessage::new("user", "Hi")]);
+        assert!(decode(&prompt).ends_with("<|start|>assistant"));
+
+        let bpe = o200k_harmony_singleton();
+        let completion = bpe.encode_with_special_tokens(
+            "<|channel|>analysis<|message|>Greet back.<|end|>\
+             <|start|>assistant<|channel|>final<|message|>Hello!<|return|>",
+        );
+        let messages = parse_completion(&completion).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].channel.as_deref(), Some("analysis"));
+        assert_eq!(messages[1].role, "assistant");
+        assert_eq!(messages[1].channel.as_deref(), Some("final"));
+        assert_eq!(messages[1].content, "Hello!");
+
+        // Recipient in the role section and content type without <|constrain|>
+        let completion = bpe.encode_with_special_tokens(
+            " to=functions.get_weather<|channel|>commentary json<|message|>{}<|call|>",
+        );
+        let call = &parse_completion(&completion).unwrap()[0];
+        assert_eq!(call.recipient.as_deref(), Some("functions.get_weather"));
+        assert_eq!(call.content_type.as_deref(), Some("json"));
+        assert!(call.is_tool_call());
+    }
+
+    #[test]
+    fn test_stream_parser_events() {
+        let bpe = o200k_harmony_singleton();
+        let tokens = bpe.encode_with_special_tokens(
+            "<|channel|>commentary to=functions.get_weather <|constrain|>json<|message|>{\"city\":\"Tokyo\"}<|call|>",
+        );
+        let mut parser = StreamParser::for_completion();
+        let mut events = Vec::new();
+        for token in tokens {
+            events.extend(parser.push(token).unwrap());
+        }
+        let header = HarmonyMessage {
+            content: String::new(),
+            ..tool_call()
+        };
+        assert_eq!(events.first(), Some(&HarmonyEvent::MessageStart(header)));
+        assert_eq!(events.last(), Some(&HarmonyEvent::ToolCall(tool_call())));
+        let content: String = events
+            .iter()
+            .filter_map(|event| match event {
+                HarmonyEvent::ContentDelta(delta) => Some(delta.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(content, tool_call().content);
+        assert!(!parser.is_done());
+        assert_eq!(parser.messages(), &[tool_call()]);
+    }
+
+    #[test]
+    fn test_stream_parser_matches_batch_parser() {
+        let messages = vec![
+            HarmonyMessage::new("user", "Quel temps fait-il à Tōkyō ? 🌧"),
+            HarmonyMessage {
+                channel: Some("final".to_string()),
+                ..HarmonyMessage::new("assistant", "Il pleut. ☔")
+            },
+        ];
+        let mut parser = StreamParser::new();
+        for token in render_conversation(&messages) {
+            parser.push(token).unwrap();
+            // Partial characters are held back until complete
+            if let Some(current) = parser.current() {
+                assert!(messages
+                    .iter()
+                    .any(|m| m.content.starts_with(&current.content)));
+            }
+        }
+        assert_eq!(parser.messages(), messages.as_slice());
+        assert!(parser.current().is_none());
+    }
+
+    #[test]
+    fn test_stream_parser_errors() {
+        let mut parser = StreamParser::new();
+        assert!(parser.push(MESSAGE).is_err());
+        parser.push(START).unwrap();
+        assert!(parser.push(END).is_err());
+        // The parser can continue after an error
+        parser
+            .push(o200k_harmony_singleton().encode_ordinary("user")[0])
+            .unwrap();
+        assert!(parser.push(MESSAGE).unwrap().len() == 1);
+    }
+
+    #[test]
+    fn test_stream_parser_recovers_from_invalid_utf8() {
+        let bpe = o200k_harmony_singleton();
+        let lone_lead_byte = bpe.encoder[&[0xE2][..]];
+        let mut parser = StreamParser::new();
+        let tokens = bpe.encode_with_special_tokens("<|start|>assistant<|message|>Hi");
+        for token in tokens {
+            parser.push(token).unwrap();
+        }
+        parser.push(lone_lead_byte).unwrap();
+        let deltas = parser.push(bpe.encode_ordinary(" there")[0]).unwrap();
+        assert_eq!(
+            deltas,
+            vec![HarmonyEvent::ContentDelta("\u{FFFD} there".to_string())]
+        );
+        // A lone lead byte at the end of a message is flushed too
+        parser.push(lone_lead_byte).unwrap();
+        let events = parser.push(END).unwrap();
+        let expected = HarmonyMessage::new("assistant", "Hi\u{FFFD} there\u{FFFD}");
+        assert_eq!(
+            events,
+            vec![
+                HarmonyEvent::ContentDelta("\u{FFFD}".to_string()),
+                HarmonyEvent::MessageEnd(expected.clone()),
+            ]
+        );
+        assert_eq!(parser.messages(), &[expected]);
+    }
+
+    #[test]
+    fn test_stream_parser_keeps_partial_character_on_error() {
+        let bpe = o200k_harmony_singleton();
+        let byte_token = |byte: u8| bpe.encoder[&[byte][..]];
+        let mut parser = StreamParser::new();
+        for token in bpe.encode_with_special_tokens("<|start|>assistant<|message|>") {
+            parser.push(token).unwrap();
+        }
+        // "€" is E2 82 AC
+        assert!(parser.push(byte_token(0xE2)).unwrap().is_empty());
+        assert!(parser.push(Rank::MAX).is_err());
+        assert!(parser.push(byte_token(0x82)).unwrap().is_empty());
+        assert_eq!(
+            parser.push(byte_token(0xAC)).unwrap(),
+            vec![HarmonyEvent::ContentDelta("€".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_malformed() {
+        let bpe = o200k_harmony_singleton();
+        let truncated = bpe.encode_with_special_tokens("<|channel|>final<|message|>Hel");
+        assert!(parse_completion(&truncated).is_err());
+        let no_start = bpe.encode_with_special_tokens("user<|message|>Hi<|end|>");
+        assert!(parse_messages(&no_start).is_err());
+    }
+}
@@ -0,0 +1,491 @@
+/*!
+ * tracks and fits chat history into a model's context window.
+ *
+ * Messages are counted once, with the same accounting as [`num_tokens_from_messages`], so
+ * the counts here are exactly what it returns for the same messages.
+ *
+ * [`num_tokens_from_messages`]: crate::num_tokens_from_messages
+ */
+
+use anyhow::{anyhow, Result};
+
+use crate::api::REPLY_PRIMING;
+use crate::counter::TokenCounter;
+use crate::model::get_context_size;
+use crate::{ChatCompletionRequestMessage, ChatCounter, CoreBPE, MessageTokens};
+
+/// Keeps a running token count of a conversation as messages are added, removed or replaced.
+///
+/// Each message is counted once when it is added, so tracking a long conversation costs
+/// time proportional to the new messages only. Messages are encoded with the model's
+/// tokenizer, or with any [`TokenCounter`] given to [`ConversationCounter::with_counter`].
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::history::ConversationCounter;
+/// use tiktoken_rs::{num_tokens_from_messages, ChatCompletionRequestMessage};
+///
+/// let mut messages = vec![ChatCompletionRequestMessage {
+///     role: "user".to_string(),
+///     content: Some("Hello!".into()),
+///     ..Default::default()
+/// }];
+/// let mut counter = ConversationCounter::new("gpt-4o").unwrap();
+/// counter.push(&messages[0]).unwrap();
+/// assert_eq!(counter.num_tokens(), num_tokens_from_messages("gpt-4o", &messages).unwrap());
+/// assert_eq!(counter.remaining_tokens(), Some(128_000 - counter.num_tokens()));
+/// ```
+pub struct ConversationCounter<'a, C: ?Sized = CoreBPE> {
+    counter: ChatCounter<'a, C>,
+    context_size: Option<usize>,
+    messages: Vec<MessageTokens>,
+    num_tokens: usize,
+}
+
+impl<C: ?Sized> Clone for ConversationCounter<'_, C> {
+    fn clone(&self) -> Self {
+        Self {
+            counter: self.counter.clone(),
+            context_size: self.context_size,
+            messages: self.messages.clone(),
+            num_tokens: self.num_tokens,
+        }
+    }
+}
+
+impl ConversationCounter<'static> {
+    /// Creates a counter for an empty conversation with the given model.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if chat token counting is not supported for the model
+    /// (see [`num_tokens_from_messages`](crate::num_tokens_from_messages)).
+    pub fn new(model: &str) -> Result<Self> {
+        Ok(Self::from_chat_counter(ChatCounter::new(model)?))
+    }
+}
+
+impl<'a, C: TokenCounter + ?Sized> ConversationCounter<'a, C> {
+    /// Creates a counter for an empty conversation with the given model, which encodes
+    /// messages with the given counter (see [`ChatCounter::with_counter`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the model is known not to be a chat model.
+    pub fn with_counter(model: &str, counter: &'a C) -> Result<Self> {
+        Ok(Self::from_chat_counter(ChatCounter::with_counter(
+            model, counter,
+        )?))
+    }
+
+    fn from_chat_counter(counter: ChatCounter<'a, C>) -> Self {
+        Self {
+            context_size: get_context_size(counter.model()),
+            counter,
+            messages: Vec::new(),
+            num_tokens: REPLY_PRIMING,
+        }
+    }
+
+    /// Appends a message to the conversation and returns its tokens.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message cannot be counted, e.g. it has images and the model
+    /// does not accept them. The conversation is left unchanged.
+    pub fn push(&mut self, message: &ChatCompletionRequestMessage) -> Result<MessageTokens> {
+        let tokens = self.counter.count_message(message)?;
+        self.num_tokens += tokens.total();
+        self.messages.push(tokens);
+        Ok(tokens)
+    }
+
+    /// Removes the last message of the conversation and returns its tokens.
+    pub fn pop(&mut self) -> Option<MessageTokens> {
+        let tokens = self.messages.pop()?;
+        self.num_tokens -= tokens.total();
+        Some(tokens)
+    }
+
+    /// Replaces the message at `index` and returns the tokens of the new message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `index` is out of bounds or the message cannot be counted. The
+    /// conversation is left unchanged.
+    pub fn replace(
+        &mut self,
+        index: usize,
+        message: &ChatCompletionRequestMessage,
+    ) -> Result<MessageTokens> {
+        if index >= self.messages.len() {
+            anyhow::bail!(
+                "Message index {} is out of bounds for a conversation of {} messages",
+                index,
+                self.messages.len()
+            )
+        }
+        let tokens = self.counter.count_message(message)?;
+        let old = std::mem::replace(&mut self.messages[index], tokens);
+        self.num_tokens = self.num_tokens - old.total() + tokens.total();
+        Ok(tokens)
+    }
+
+    /// Removes all messages.
+    pub fn clear(&mut self) {
+        self.messages.clear();
+        self.num_tokens = REPLY_PRIMING;
+    }
+
+    /// Returns the number of messages in the conversation.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Returns `true` if the conversation has no messages.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Returns the tokens of each message, in order.
+    pub fn message_tokens(&self) -> &[MessageTokens] {
+        &self.messages
+    }
+
+    /// Returns the number of prompt tokens of the conversation, including the reply priming.
+    pub fn num_tokens(&self) -> usize {
+        self.num_tokens
+    }
+
+    /// Returns the number of tokens left in the model's context window, or `None` if the
+    /// context size of the model is unknown.
+    pub fn remaining_tokens(&self) -> Option<usize> {
+        self.context_size
+            .map(|context_size| context_size.saturating_sub(self.num_tokens))
+    }
+}
+
+/// Controls which messages [`fit_messages_to_context`] may drop.
+///
+/// Messages that are not protected by the policy are dropped oldest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TrimPolicy {
+    /// Never drop `system` and `developer` messages.
+    pub keep_system: bool,
+    /// Never drop the last `keep_last_turns` turns. A turn starts at a `user` message.
+    pub keep_last_turns: usize,
+    /// Drop an assistant message with tool or function calls together with the results that
+    /// follow it, so that no call is left without its result or the other way around.
+    pub keep_tool_pairs: bool,
+}
+
+impl Default for TrimPolicy {
+    fn default() -> Self {
+        Self {
+            keep_system: true,
+            keep_last_turns: 1,
+            keep_tool_pairs: true,
+        }
+    }
+}
+
+/// The messages kept by [`fit_messages_to_context`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FittedHistory {
+    /// The kept messages, in their original order.
+    pub messages: Vec<ChatCompletionRequestMessage>,
+    /// The indices of the kept messages in the original slice.
+    pub kept: Vec<usize>,
+    /// The number of prompt tokens of the kept messages.
+    pub num_tokens: usize,
+}
+
+/// Drops the oldest messages until the prompt leaves room for `completion_reserve` tokens in the
+/// model's context window.
+///
+/// # Arguments
+///
+/// * `model` - A string slice representing the model name, e.g., `"gpt-4o"`.
+/// * `messages` - The conversation, oldest message first.
+/// * `completion_reserve` - The number of tokens to leave for the completion.
+/// * `policy` - Which messages must be kept.
+///
+/// # Errors
+///
+/// Returns an error if the messages cannot be counted for the model
+/// (see [`num_tokens_from_messages`](crate::num_tokens_from_messages)), if the context size of
+/// the model is unknown, or if the messages protected by the policy do not fit.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::history::{fit_messages_to_context, TrimPolicy};
+/// use tiktoken_rs::ChatCompletionRequestMessage;
+///
+/// let message = |role: &str, content: &str| ChatCompletionRequestMessage {
+///     role: role.to_string(),
+///     content: Some(content.into()),
+///     ..Default::default()
+/// };
+/// let mut messages = vec![message("system", "You are a helpful assistant.")];
+/// for _ in 0..2000 {
+///     messages.push(message("user", "Tell me a long story."));
+///     messages.push(message("assistant", &"Once upon a time. ".repeat(20)));
+/// }
+/// let fitted = fit_messages_to_context("gpt-4", &messages, 1000, &TrimPolicy::default()).unwrap();
+/// assert!(fitted.num_tokens + 1000 <= 8192);
+/// assert_eq!(fitted.kept[0], 0);
+/// assert_eq!(fitted.kept.last(), Some(&(messages.len() - 1)));
+/// ```
+pub fn fit_messages_to_context(
+    model: &str,
+    messages: &[ChatCompletionRequestMessage],
+    completion_reserve: usize,
+    policy: &TrimPolicy,
+) -> Result<FittedHistory> {
+    ChatCounter::new(model)?.fit_messages_to_context(messages, completion_reserve, policy)
+}
+
+impl<C: TokenCounter + ?Sized> ChatCounter<'_, C> {
+    /// Drops the oldest messages until the prompt leaves room for `completion_reserve` tokens,
+    /// see [`fit_messages_to_context`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the messages cannot be counted for the model, if the context size
+    /// of the model is unknown, or if the messages protected by the policy do not fit.
+    pub fn fit_messages_to_context(
+        &self,
+        messages: &[ChatCompletionRequestMessage],
+        completion_reserve: usize,
+        policy: &TrimPolicy,
+    ) -> Result<FittedHistory> {
+        let model = self.model();
+        let context_size = get_context_size(model)
+            .ok_or_else(|| anyhow!("Unknown context size for model {}", model))?;
+        let budget = context_size.saturating_sub(completion_reserve);
+        let breakdown = self.breakdown(messages)?;
+        let mut num_tokens = breakdown.total();
+
+        let protected_from = last_turns_start(messages, policy.keep_last_turns);
+        let mut kept = vec![true; messages.len()];
+        for (start, end) in message_groups(messages, policy.keep_tool_pairs) {
+            if num_tokens <= budget {
+                break;
+            }
+            let protected = (start..end).any(|i| {
+                i >= protected_from
+                    || (policy.keep_system
+                        && matches!(messages[i].role.as_str(), "system" | "developer"))
+            });
+            if protected {
+                continue;
+            }
+            kept[start..end].fill(false);
+            num_tokens -= breakdown.messages[start..end]
+                .iter()
+                .map(|tokens| tokens.total())
+                .sum::<usize>();
+        }
+        if num_tokens > budget {
+            anyhow::bail!(
+                "The kept messages need {} tokens, but only {} are available for model {} \
+             after reserving {} for the completion",
+                num_tokens,
+                budget,
+                model,
+                completion_reserve
+            )
+        }
+
+        let kept: Vec<usize> = (0..messages.len()).filter(|&i| kept[i]).collect();
+        Ok(FittedHistory {
+            messages: kept.iter().map(|&i| messages[i].clone()).collect(),
+            kept,
+            num_tokens,
+        })
+    }
+}
+
+/// Returns the index of the first message of the last `turns` turns, or `messages.len()` if
+/// no turn is kept.
+fn last_turns_start(messages: &[ChatCompletionRequestMessage], turns: usize) -> usize {
+    if turns == 0 {
+        return messages.len();
+    }
+    messages
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, message)| message.role == "user")
+        .nth(turns - 1)
+        .map_or(0, |(i, _)| i)
+}
+
+/// Splits the messages into the ranges that are dropped together, oldest first.
+fn message_groups(
+    messages: &[ChatCompletionRequestMessage],
+    keep_tool_pairs: bool,
+) -> Vec<(usize, usize)> {
+    let mut groups = Vec::new();
+    let mut start = 0;
+    while start < messages.len() {
+        let message = &messages[start];
+        let mut end = start + 1;
+        let has_calls = message.function_call.is_some() || !message.tool_calls.is_empty();
+        if keep_tool_pairs && message.role == "assistant" && has_calls {
+            while end < messages.len() && matches!(messages[end].role.as_str(), "tool" | "function")
+            {
+                end += 1;
+            }
+        }
+        groups.push((start, end));
+        start = end;
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{num_tokens_from_messages, ToolCall};
+
+    fn message(role: &str, content: &str) -> ChatCompletionRequestMessage {
+        ChatCompletionRequestMessage {
+            role: role.to_string(),
+            content: Some(content.into()),
+            ..Default::default()
+        }
+    }
+
+    /// A conversation where the long messages are large enough that only two of them fit in
+    /// gpt-4's context.
+    fn conversation() -> Vec<ChatCompletionRequestMessage> {
+        let long = "word ".repeat(2500);
+        vec![
+            message("system", "You are a helpful assistant."),
+            message("user", "Search for rust."),
+            ChatCompletionRequestMessage {
+                role: "assistant".to_string(),
+                tool_calls: vec![ToolCall::function("call_1", "search", long.clone())],
+                ..Default::default()
+            },
+            ChatCompletionRequestMessage {
+                tool_call_id: Some("call_1".to_string()),
+                ..message("tool", "No results.")
+            },
+            message("assistant", &long),
+            message("user", &long),
+            message("assistant", "Done."),
+        ]
+    }
+
+    #[test]
+    fn test_conversation_counter() {
+        let messages = conversation();
+        let mut counter = ConversationCounter::new("gpt-4").unwrap();
+        assert_eq!(
+            counter.num_tokens(),
+            num_tokens_from_messages("gpt-4", &[]).unwrap()
+        );
+        for message in &messages {
+            counter.push(message).unwrap();
+        }
+        assert_eq!(counter.len(), messages.len());
+        assert_eq!(
+            counter.num_tokens(),
+            num_tokens_from_messages("gpt-4", &messages).unwrap()
+        );
+        assert_eq!(
+            counter.remaining_tokens(),
+            Some(8192 - counter.num_tokens())
+        );
+
+        let replacement = message("user", "Never mind.");
+        counter.replace(5, &replacement).unwrap();
+        counter.pop().unwrap();
+        let mut expected = messages[..6].to_vec();
+        expected[5] = replacement;
+        assert_eq!(
+            counter.num_tokens(),
+            num_tokens_from_messages("gpt-4", &expected).unwrap()
+        );
+
+        assert!(counter.replace(6, &message("user", "Hi")).is_err());
+        let with_image = ChatCompletionRequestMessage {
+            content: Some(vec![crate::multimodal::ImageInput::new(512, 512).into()].into()),
+            ..message("user", "What is this?")
+        };
+        assert!(counter.push(&with_image).is_err());
+        assert_eq!(counter.len(), 6);
+
+        counter.clear();
+        assert!(counter.is_empty());
+        assert!(ConversationCounter::new("text-embedding-3-small").is_err());
+
+        // Models without a known tokenizer can be counted with another encoding
+        let bpe = crate::cl100k_base_singleton();
+        let mut counter = ConversationCounter::with_counter("my-model", bpe).unwrap();
+        for message in &messages {
+            counter.push(message).unwrap();
+        }
+        assert_eq!(
+            counter.num_tokens(),
+            num_tokens_from_messages("gpt-4", &messages).unwrap()
+        );
+        assert_eq!(counter.remaining_tokens(), None);
+    }
+
+    #[test]
+    fn test_no_trimming_needed() {
+        let messages = vec![message("user", "Hi")];
+        let fitted =
+            fit_messages_to_context("gpt-4o", &messages, 100, &TrimPolicy::default()).unwrap();
+        assert_eq!(fitted.messages, messages);
+        assert_eq!(
+            fitted.num_tokens,
+            num_tokens_from_messages("gpt-4o", &messages).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_drops_oldest_with_tool_pairs() {
+        let messages = conversation();
+        let fitted =
+            fit_messages_to_context("gpt-4", &messages, 1000, &TrimPolicy::default()).unwrap();
+        // The tool call is dropped together with its result
+        assert_eq!(fitted.kept, vec![0, 4, 5, 6]);
+        assert_eq!(
+            fitted.num_tokens,
+            num_tokens_from_messages("gpt-4", &fitted.messages).unwrap()
+        );
+        assert!(fitted.num_tokens + 1000 <= 8192);
+
+        let policy = TrimPolicy {
+            keep_tool_pairs: false,
+            ..TrimPolicy::default()
+        };
+        let fitted = fit_messages_to_context("gpt-4", &messages, 1000, &policy).unwrap();
+        assert_eq!(fitted.kept, vec![0, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_policy_that_cannot_fit() {
+        let messages = conversation();
+        let policy = TrimPolicy {
+            keep_last_turns: 2,
+            ..TrimPolicy::default()
+        };
+        assert!(fit_messages_to_context("gpt-4", &messages, 1000, &policy).is_err());
+        assert!(fit_messages_to_context("gpt-4o", &messages, 1000, &policy).is_ok());
+
+        let policy = TrimPolicy {
+            keep_system: false,
+            keep_last_turns: 0,
+            ..TrimPolicy::default()
+        };
+        let fitted = fit_messages_to_context("gpt-4", &messages, 7000, &policy).unwrap();
+        assert_eq!(fitted.kept, vec![6]);
+    }
+}
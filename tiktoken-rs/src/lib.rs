@@ -4,10 +4,24 @@ mod patched_tiktoken;
 mod singleton;
 mod tiktoken_ext;
 mod vendor_tiktoken;
+#[cfg(feature = "serde")]
+mod wire;
 
 pub use api::*;
+pub mod chatml;
+pub mod counter;
+pub mod harmony;
+pub mod history;
 pub mod model;
+pub mod multimodal;
+#[cfg(feature = "serde")]
+pub mod request_json;
+pub mod responses;
 pub mod tokenizer;
+#[cfg(feature = "tokenizers-compat")]
+pub mod tokenizers_compat;
+#[cfg(feature = "tools")]
+pub mod tools;
 pub use singleton::*;
 pub use tiktoken_ext::openai_public::*;
 
@@ -1,7 +1,13 @@
 /*!
- * contains information about OpenAI models.
+ * contains information about OpenAI models, and token estimation profiles for the models of
+ * other providers.
  */
 
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::tokenizer::Tokenizer;
+
 /// Macro to check if a given str starts with any of the specified prefixes.
 macro_rules! starts_with_any {
     ($str:expr, $($prefix:expr),* $(,)?) => {
@@ -15,6 +21,9 @@ macro_rules! starts_with_any {
 /// This function checks the model name and returns the corresponding context size.
 /// See <https://platform.openai.com/docs/models> for up-to-date information.
 ///
+/// Only OpenAI models are known. The context size of models of other providers is in their
+/// [`EstimationProfile`].
+///
 /// # Arguments
 ///
 /// * `model` - A string slice that holds the name of the model.
@@ -64,7 +73,7 @@ pub fn get_context_size(model: &str) -> Option<usize> {
     if starts_with_any!(model, "gpt-4.1") {
         return Some(1_047_576);
     }
-    if starts_with_any!(model, "chatgpt-4o", "gpt-4o") {
+    if starts_with_any!(model, "chatgpt-4o", "gpt-4o", "gpt-audio") {
         return Some(128_000);
     }
     if starts_with_any!(model, "gpt-4.5") {
@@ -115,8 +124,997 @@ pub fn get_context_size(model: &str) -> Option<usize> {
     if starts_with_any!(model, "text-ada-001", "text-babbage-001", "text-curie-001") {
         return Some(2049);
     }
-    if starts_with_any!(model, "text-embedding-ada-002") {
+    if starts_with_any!(model, "text-embedding-ada-002", "text-embedding-3-") {
         return Some(8192);
     }
     None
 }
+
+/// Returns the maximum number of output tokens a specified model can generate in one response.
+///
+/// For reasoning models this limit covers both the hidden reasoning tokens and the visible
+/// output, and it is usually lower than the context size returned by [`get_context_size`].
+/// See <https://platform.openai.com/docs/models> for up-to-date information.
+///
+/// # Arguments
+///
+/// * `model` - A string slice that holds the name of the model.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::model::get_max_output_tokens;
+/// assert_eq!(get_max_output_tokens("o3-mini"), Some(100_000));
+/// assert_eq!(get_max_output_tokens("gpt-4o"), Some(16_384));
+/// ```
+///
+/// # Returns
+///
+/// Returns `None` if the model is not recognized.
+pub fn get_max_output_tokens(model: &str) -> Option<usize> {
+    if let Some(rest) = model.strip_prefix("ft:") {
+        let base = rest.split(':').next().unwrap_or(rest);
+        return get_max_output_tokens(base);
+    }
+    if starts_with_any!(model, "gpt-5-chat", "gpt-5.1-chat", "gpt-5.2-chat") {
+        return Some(16_384);
+    }
+    if starts_with_any!(model, "gpt-5.3-codex-spark") {
+        return Some(32_768);
+    }
+    if starts_with_any!(model, "gpt-5") {
+        return Some(128_000);
+    }
+    if starts_with_any!(model, "codex-mini") {
+        return Some(100_000);
+    }
+    if starts_with_any!(model, "gpt-oss") {
+        return Some(131_072);
+    }
+    if starts_with_any!(model, "o1-mini") {
+        return Some(65_536);
+    }
+    if starts_with_any!(model, "o1-preview") {
+        return Some(32_768);
+    }
+    if starts_with_any!(model, "o1", "o3", "o4") {
+        return Some(100_000);
+    }
+    if starts_with_any!(model, "gpt-4.1") {
+        return Some(32_768);
+    }
+    if starts_with_any!(model, "gpt-4o-2024-05-13") {
+        return Some(4_096);
+    }
+    if starts_with_any!(model, "chatgpt-4o", "gpt-4o", "gpt-4.5", "gpt-audio") {
+        return Some(16_384);
+    }
+    if starts_with_any!(model, "gpt-4-turbo", "gpt-4-0125", "gpt-4-1106") {
+        return Some(4_096);
+    }
+    if starts_with_any!(model, "gpt-4-32k") {
+        return Some(32_768);
+    }
+    if starts_with_any!(model, "gpt-4") {
+        return Some(8_192);
+    }
+    if starts_with_any!(model, "gpt-3.5-turbo", "gpt-3.5", "gpt-35-turbo") {
+        return Some(4_096);
+    }
+    None
+}
+
+/// How a vision model converts image inputs into tokens.
+///
+/// See <https://platform.openai.com/docs/guides/images-vision#calculating-costs>.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageTokenCost {
+    /// The image is resized and cut into 512px tiles. Low detail images cost `base_tokens`,
+    /// high detail images cost `base_tokens + tiles * tile_tokens`.
+    Tiles {
+        base_tokens: usize,
+        tile_tokens: usize,
+    },
+    /// The image is covered with 32px patches, capped at 1536 patches, and the patch count is
+    /// scaled by `multiplier`.
+    Patches { multiplier: f64 },
+}
+
+/// Returns how a specified model converts image inputs into tokens.
+///
+/// # Arguments
+///
+/// * `model` - A string slice that holds the name of the model.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::model::{get_image_token_cost, ImageTokenCost};
+/// assert_eq!(
+///     get_image_token_cost("gpt-4o"),
+///     Some(ImageTokenCost::Tiles { base_tokens: 85, tile_tokens: 170 })
+/// );
+/// ```
+///
+/// # Returns
+///
+/// Returns `None` if the model is not recognized or does not accept images.
+pub fn get_image_token_cost(model: &str) -> Option<ImageTokenCost> {
+    if let Some(rest) = model.strip_prefix("ft:") {
+        let base = rest.split(':').next().unwrap_or(rest);
+        return get_image_token_cost(base);
+    }
+    let tiles = |base_tokens, tile_tokens| {
+        Some(ImageTokenCost::Tiles {
+            base_tokens,
+            tile_tokens,
+        })
+    };
+    let patches = |multiplier| Some(ImageTokenCost::Patches { multiplier });
+    if starts_with_any!(model, "gpt-5-mini", "gpt-5.1-codex-mini", "gpt-5.4-mini") {
+        return patches(1.62);
+    }
+    if starts_with_any!(model, "gpt-5-nano", "gpt-5.4-nano") {
+        return patches(2.46);
+    }
+    if starts_with_any!(model, "gpt-5.3-codex-spark") {
+        return None;
+    }
+    if starts_with_any!(model, "gpt-5") {
+        return tiles(70, 140);
+    }
+    if starts_with_any!(model, "o4-mini", "codex-mini") {
+        return patches(1.72);
+    }
+    if starts_with_any!(model, "o1-mini", "o1-preview", "o3-mini") {
+        return None;
+    }
+    if starts_with_any!(model, "o1", "o3") {
+        return tiles(75, 150);
+    }
+    if starts_with_any!(model, "gpt-4.1-mini") {
+        return patches(1.62);
+    }
+    if starts_with_any!(model, "gpt-4.1-nano") {
+        return patches(2.46);
+    }
+    if starts_with_any!(model, "gpt-4o-mini") {
+        return tiles(2833, 5667);
+    }
+    if starts_with_any!(
+        model,
+        "gpt-4.1",
+        "gpt-4o",
+        "chatgpt-4o",
+        "gpt-4.5",
+        "gpt-4-vision",
+        "gpt-4-1106-vision",
+    ) {
+        return tiles(85, 170);
+    }
+    if starts_with_any!(model, "gpt-4-turbo-preview") {
+        return None;
+    }
+    if starts_with_any!(model, "gpt-4-turbo") {
+        return tiles(85, 170);
+    }
+    None
+}
+
+/// Returns the number of input tokens per second of audio for a specified model.
+///
+/// # Arguments
+///
+/// * `model` - A string slice that holds the name of the model.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::model::get_audio_tokens_per_second;
+/// assert_eq!(get_audio_tokens_per_second("gpt-4o-audio-preview"), Some(10.0));
+/// assert_eq!(get_audio_tokens_per_second("gpt-4o"), None);
+/// ```
+///
+/// # Returns
+///
+/// Returns `None` if the model is not recognized or does not accept audio.
+pub fn get_audio_tokens_per_second(model: &str) -> Option<f64> {
+    if let Some(rest) = model.strip_prefix("ft:") {
+        let base = rest.split(':').next().unwrap_or(rest);
+        return get_audio_tokens_per_second(base);
+    }
+    // User audio is billed at one token per 100ms
+    if starts_with_any!(model, "gpt-4o-audio", "gpt-4o-mini-audio", "gpt-audio") {
+        return Some(10.0);
+    }
+    None
+}
+
+/// Prices for a model, in US dollars per million tokens.
+///
+/// Built-in values come from <https://openai.com/api/pricing> and are a snapshot; prices change
+/// over time, so construct your own `ModelPricing` when you need exact figures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    /// Price per million input (prompt) tokens.
+    pub input: f64,
+    /// Price per million cached input tokens, or `None` if the model has no prompt caching discount.
+    pub cached_input: Option<f64>,
+    /// Price per million output (completion) tokens.
+    pub output: f64,
+}
+
+impl ModelPricing {
+    /// Creates a new pricing entry. All prices are in US dollars per million tokens.
+    pub const fn new(input: f64, cached_input: Option<f64>, output: f64) -> Self {
+        Self {
+            input,
+            cached_input,
+            output,
+        }
+    }
+
+    /// Estimates the cost of a request with the given token counts.
+    ///
+    /// `cached_input_tokens` is the portion of `input_tokens` served from the prompt cache,
+    /// matching how the API reports `cached_tokens` as a subset of `prompt_tokens`. If the
+    /// model has no cached input price, cached tokens are billed at the regular input price.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tiktoken_rs::model::ModelPricing;
+    ///
+    /// let pricing = ModelPricing::new(2.50, Some(1.25), 10.00);
+    /// let cost = pricing.estimate(1_000_000, 0, 100_000);
+    /// assert_eq!(cost.total_cost(), 3.50);
+    /// ```
+    pub fn estimate(
+        &self,
+        input_tokens: usize,
+        cached_input_tokens: usize,
+        output_tokens: usize,
+    ) -> CostEstimate {
+        let cached_input_tokens = cached_input_tokens.min(input_tokens);
+        let uncached_input_tokens = input_tokens - cached_input_tokens;
+        let cost = |tokens: usize, price: f64| tokens as f64 * price / 1_000_000.0;
+        CostEstimate {
+            input_tokens,
+            cached_input_tokens,
+            output_tokens,
+            input_cost: cost(uncached_input_tokens, self.input),
+            cached_input_cost: cost(cached_input_tokens, self.cached_input.unwrap_or(self.input)),
+            output_cost: cost(output_tokens, self.output),
+        }
+    }
+}
+
+/// The estimated cost of a request, in US dollars.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CostEstimate {
+    /// Total number of input tokens, including cached ones.
+    pub input_tokens: usize,
+    /// Number of input tokens billed at the cached input price.
+    pub cached_input_tokens: usize,
+    /// Number of output tokens.
+    pub output_tokens: usize,
+    /// Cost of the uncached input tokens.
+    pub input_cost: f64,
+    /// Cost of the cached input tokens.
+    pub cached_input_cost: f64,
+    /// Cost of the output tokens.
+    pub output_cost: f64,
+}
+
+impl CostEstimate {
+    /// Returns the sum of the input, cached input and output costs.
+    pub fn total_cost(&self) -> f64 {
+        self.input_cost + self.cached_input_cost + self.output_cost
+    }
+}
+
+/// Returns the list prices of a specified model.
+///
+/// Models are matched by prefix in the same way as [`get_context_size`], so dated snapshots
+/// such as `gpt-4o-2024-08-06` resolve to their family's current price.
+/// See <https://openai.com/api/pricing> for up-to-date information.
+///
+/// # Arguments
+///
+/// * `model` - A string slice that holds the name of the model.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::model::get_model_pricing;
+/// let pricing = get_model_pricing("gpt-4o-mini").unwrap();
+/// assert_eq!(pricing.input, 0.15);
+/// assert_eq!(pricing.output, 0.60);
+/// ```
+///
+/// # Returns
+///
+/// Returns `None` if the model is not recognized. Fine-tuned models (`ft:` prefix) are billed
+/// at different rates than their base model and also return `None`; supply a [`ModelPricing`]
+/// explicitly for those.
+pub fn get_model_pricing(model: &str) -> Option<ModelPricing> {
+    if model.starts_with("ft:") {
+        return None;
+    }
+    if starts_with_any!(model, "gpt-5.4-pro") {
+        return Some(ModelPricing::new(30.00, None, 180.00));
+    }
+    if starts_with_any!(model, "gpt-5.4-mini") {
+        return Some(ModelPricing::new(0.75, Some(0.075), 4.50));
+    }
+    if starts_with_any!(model, "gpt-5.4-nano") {
+        return Some(ModelPricing::new(0.20, Some(0.02), 1.25));
+    }
+    if starts_with_any!(model, "gpt-5.4") {
+        return Some(ModelPricing::new(2.50, Some(0.25), 15.00));
+    }
+    if starts_with_any!(model, "gpt-5.2-pro") {
+        return Some(ModelPricing::new(21.00, None, 168.00));
+    }
+    if starts_with_any!(model, "gpt-5.2", "gpt-5.3") {
+        return Some(ModelPricing::new(1.75, Some(0.175), 14.00));
+    }
+    if starts_with_any!(model, "gpt-5-pro") {
+        return Some(ModelPricing::new(15.00, None, 120.00));
+    }
+    if starts_with_any!(model, "gpt-5-mini", "gpt-5.1-codex-mini") {
+        return Some(ModelPricing::new(0.25, Some(0.025), 2.00));
+    }
+    if starts_with_any!(model, "gpt-5-nano") {
+        return Some(ModelPricing::new(0.05, Some(0.005), 0.40));
+    }
+    if starts_with_any!(model, "gpt-5") {
+        return Some(ModelPricing::new(1.25, Some(0.125), 10.00));
+    }
+    if starts_with_any!(model, "codex-mini") {
+        return Some(ModelPricing::new(1.50, Some(0.375), 6.00));
+    }
+    if starts_with_any!(model, "o1-pro") {
+        return Some(ModelPricing::new(150.00, None, 600.00));
+    }
+    if starts_with_any!(model, "o1-mini", "o3-mini") {
+        return Some(ModelPricing::new(1.10, Some(0.55), 4.40));
+    }
+    if starts_with_any!(model, "o1") {
+        return Some(ModelPricing::new(15.00, Some(7.50), 60.00));
+    }
+    if starts_with_any!(model, "o3-pro") {
+        return Some(ModelPricing::new(20.00, None, 80.00));
+    }
+    if starts_with_any!(model, "o3") {
+        return Some(ModelPricing::new(2.00, Some(0.50), 8.00));
+    }
+    if starts_with_any!(model, "o4-mini") {
+        return Some(ModelPricing::new(1.10, Some(0.275), 4.40));
+    }
+    if starts_with_any!(model, "gpt-4.1-mini") {
+        return Some(ModelPricing::new(0.40, Some(0.10), 1.60));
+    }
+    if starts_with_any!(model, "gpt-4.1-nano") {
+        return Some(ModelPricing::new(0.10, Some(0.025), 0.40));
+    }
+    if starts_with_any!(model, "gpt-4.1") {
+        return Some(ModelPricing::new(2.00, Some(0.50), 8.00));
+    }
+    if starts_with_any!(model, "gpt-4o-mini") {
+        return Some(ModelPricing::new(0.15, Some(0.075), 0.60));
+    }
+    if starts_with_any!(model, "chatgpt-4o", "gpt-4o-2024-05-13") {
+        return Some(ModelPricing::new(5.00, None, 15.00));
+    }
+    if starts_with_any!(model, "gpt-4o") {
+        return Some(ModelPricing::new(2.50, Some(1.25), 10.00));
+    }
+    if starts_with_any!(model, "gpt-4.5") {
+        return Some(ModelPricing::new(75.00, Some(37.50), 150.00));
+    }
+    if starts_with_any!(model, "gpt-4-turbo", "gpt-4-0125", "gpt-4-1106") {
+        return Some(ModelPricing::new(10.00, None, 30.00));
+    }
+    if starts_with_any!(model, "gpt-4-32k") {
+        return Some(ModelPricing::new(60.00, None, 120.00));
+    }
+    if starts_with_any!(model, "gpt-4") {
+        return Some(ModelPricing::new(30.00, None, 60.00));
+    }
+    if starts_with_any!(model, "gpt-3.5-turbo-instruct") {
+        return Some(ModelPricing::new(1.50, None, 2.00));
+    }
+    if starts_with_any!(model, "gpt-3.5-turbo", "gpt-3.5", "gpt-35-turbo") {
+        return Some(ModelPricing::new(0.50, None, 1.50));
+    }
+    if starts_with_any!(model, "davinci-002") {
+        return Some(ModelPricing::new(2.00, None, 2.00));
+    }
+    if starts_with_any!(model, "babbage-002") {
+        return Some(ModelPricing::new(0.40, None, 0.40));
+    }
+    if starts_with_any!(model, "text-embedding-3-small") {
+        return Some(ModelPricing::new(0.02, None, 0.0));
+    }
+    if starts_with_any!(model, "text-embedding-3-large") {
+        return Some(ModelPricing::new(0.13, None, 0.0));
+    }
+    if starts_with_any!(model, "text-embedding-ada-002") {
+        return Some(ModelPricing::new(0.10, None, 0.0));
+    }
+    None
+}
+
+/// The features a model supports through the OpenAI API.
+///
+/// Use [`get_model_capabilities`] to look up the capabilities of a model by name.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModelCapabilities {
+    /// Accepts a list of chat messages (Chat Completions or Responses API).
+    pub chat: bool,
+    /// Accepts a single prompt string (legacy Completions API).
+    pub completions: bool,
+    /// Produces embeddings (Embeddings API).
+    pub embeddings: bool,
+    /// Spends hidden reasoning tokens before answering.
+    pub reasoning: bool,
+    /// Accepts tool (function) definitions.
+    pub tools: bool,
+    /// Accepts image inputs.
+    pub vision: bool,
+    /// Accepts audio inputs.
+    pub audio: bool,
+}
+
+const CHAT: ModelCapabilities = ModelCapabilities {
+    chat: true,
+    completions: false,
+    embeddings: false,
+    reasoning: false,
+    tools: false,
+    vision: false,
+    audio: false,
+};
+const CHAT_TOOLS: ModelCapabilities = ModelCapabilities {
+    tools: true,
+    ..CHAT
+};
+const CHAT_TOOLS_VISION: ModelCapabilities = ModelCapabilities {
+    vision: true,
+    ..CHAT_TOOLS
+};
+const REASONING: ModelCapabilities = ModelCapabilities {
+    reasoning: true,
+    ..CHAT
+};
+const REASONING_TOOLS: ModelCapabilities = ModelCapabilities {
+    tools: true,
+    ..REASONING
+};
+const REASONING_TOOLS_VISION: ModelCapabilities = ModelCapabilities {
+    vision: true,
+    ..REASONING_TOOLS
+};
+const COMPLETIONS: ModelCapabilities = ModelCapabilities {
+    chat: false,
+    completions: true,
+    ..CHAT
+};
+const EMBEDDINGS: ModelCapabilities = ModelCapabilities {
+    chat: false,
+    embeddings: true,
+    ..CHAT
+};
+
+/// Returns the capabilities of a specified model.
+///
+/// Models are matched by prefix in the same way as [`get_context_size`], and fine-tuned models
+/// (`ft:` prefix) share the capabilities of their base model.
+/// See <https://platform.openai.com/docs/models> for up-to-date information.
+///
+/// # Arguments
+///
+/// * `model` - A string slice that holds the name of the model.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::model::get_model_capabilities;
+/// let capabilities = get_model_capabilities("gpt-4o").unwrap();
+/// assert!(capabilities.chat);
+/// assert!(capabilities.vision);
+///
+/// let capabilities = get_model_capabilities("davinci-002").unwrap();
+/// assert!(!capabilities.chat);
+/// ```
+///
+/// # Returns
+///
+/// Returns `None` if the model is not recognized.
+pub fn get_model_capabilities(model: &str) -> Option<ModelCapabilities> {
+    if let Some(rest) = model.strip_prefix("ft:") {
+        let base = rest.split(':').next().unwrap_or(rest);
+        return get_model_capabilities(base);
+    }
+    if starts_with_any!(model, "gpt-5.3-codex-spark") {
+        return Some(REASONING_TOOLS);
+    }
+    if starts_with_any!(model, "gpt-5-chat", "gpt-5.1-chat", "gpt-5.2-chat") {
+        return Some(CHAT_TOOLS_VISION);
+    }
+    if starts_with_any!(model, "gpt-5", "codex-mini") {
+        return Some(REASONING_TOOLS_VISION);
+    }
+    if starts_with_any!(model, "gpt-oss") {
+        return Some(REASONING_TOOLS);
+    }
+    if starts_with_any!(model, "o1-mini", "o1-preview") {
+        return Some(REASONING);
+    }
+    if starts_with_any!(model, "o3-mini") {
+        return Some(REASONING_TOOLS);
+    }
+    if starts_with_any!(model, "o1", "o3", "o4") {
+        return Some(REASONING_TOOLS_VISION);
+    }
+    if starts_with_any!(model, "chatgpt-4o") {
+        return Some(ModelCapabilities {
+            vision: true,
+            ..CHAT
+        });
+    }
+    if starts_with_any!(model, "gpt-4o-audio", "gpt-4o-mini-audio", "gpt-audio") {
+        return Some(ModelCapabilities {
+            audio: true,
+            ..CHAT_TOOLS
+        });
+    }
+    if starts_with_any!(model, "gpt-4.1", "gpt-4o", "gpt-4.5") {
+        return Some(CHAT_TOOLS_VISION);
+    }
+    if starts_with_any!(
+        model,
+        "gpt-4-turbo-preview",
+        "gpt-4-0125",
+        "gpt-4-1106-preview"
+    ) {
+        return Some(CHAT_TOOLS);
+    }
+    if starts_with_any!(model, "gpt-4-turbo") {
+        return Some(CHAT_TOOLS_VISION);
+    }
+    if starts_with_any!(model, "gpt-4-vision", "gpt-4-1106-vision") {
+        return Some(ModelCapabilities {
+            vision: true,
+            ..CHAT
+        });
+    }
+    if starts_with_any!(model, "gpt-4-0314", "gpt-4-32k-0314") {
+        return Some(CHAT);
+    }
+    if starts_with_any!(model, "gpt-4") {
+        return Some(CHAT_TOOLS);
+    }
+    if starts_with_any!(model, "gpt-3.5-turbo-instruct") {
+        return Some(COMPLETIONS);
+    }
+    if starts_with_any!(model, "gpt-3.5-turbo-0301") {
+        return Some(CHAT);
+    }
+    if starts_with_any!(model, "gpt-3.5-turbo", "gpt-3.5", "gpt-35-turbo") {
+        return Some(CHAT_TOOLS);
+    }
+    if starts_with_any!(
+        model,
+        "text-embedding-",
+        "text-similarity-",
+        "text-search-",
+        "code-search-",
+    ) {
+        return Some(EMBEDDINGS);
+    }
+    if starts_with_any!(model, "text-davinci-edit", "code-davinci-edit") {
+        return Some(ModelCapabilities::default());
+    }
+    if starts_with_any!(
+        model,
+        "davinci-002",
+        "babbage-002",
+        "text-davinci-",
+        "text-curie-",
+        "text-babbage-",
+        "text-ada-",
+        "code-davinci-",
+        "code-cushman-",
+        "davinci-codex",
+        "cushman-codex",
+    ) {
+        return Some(COMPLETIONS);
+    }
+    if matches!(model, "davinci" | "curie" | "babbage" | "ada") {
+        return Some(COMPLETIONS);
+    }
+    None
+}
+
+/// Token limits of the embeddings endpoint for a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EmbeddingLimits {
+    /// Maximum number of tokens in a single input.
+    pub max_input_tokens: usize,
+    /// Maximum number of inputs in a single request.
+    pub max_inputs_per_request: usize,
+    /// Maximum number of tokens summed over all inputs of a single request.
+    pub max_tokens_per_request: usize,
+}
+
+/// Returns the embeddings endpoint limits of a specified model.
+///
+/// See <https://platform.openai.com/docs/api-reference/embeddings/create> for up-to-date information.
+///
+/// # Arguments
+///
+/// * `model` - A string slice that holds the name of the embedding model.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::model::get_embedding_limits;
+/// let limits = get_embedding_limits("text-embedding-3-small").unwrap();
+/// assert_eq!(limits.max_input_tokens, 8192);
+/// assert_eq!(limits.max_inputs_per_request, 2048);
+/// assert_eq!(limits.max_tokens_per_request, 300_000);
+/// ```
+///
+/// # Returns
+///
+/// Returns `None` if the model is not a recognized embedding model.
+pub fn get_embedding_limits(model: &str) -> Option<EmbeddingLimits> {
+    if starts_with_any!(model, "text-embedding-ada-002", "text-embedding-3-") {
+        return Some(EmbeddingLimits {
+            max_input_tokens: 8192,
+            max_inputs_per_request: 2048,
+            max_tokens_per_request: 300_000,
+        });
+    }
+    None
+}
+
+/// A provider of models that are not OpenAI models.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Provider {
+    Anthropic,
+    DeepSeek,
+    Google,
+    Meta,
+    Mistral,
+}
+
+/// How token counts are estimated for a model of another provider, whose tokenizer is not
+/// available in this crate.
+///
+/// Counts are made with an OpenAI encoding and multiplied by a correction factor, so they are
+/// **estimates**: the error depends on the language and kind of text. The built-in factors are
+/// rough averages over English prose and code; for tighter estimates, calibrate a profile
+/// against the provider's token counting endpoint for your own traffic.
+///
+/// Use [`get_estimation_profile`] to look up the profile of a model by name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EstimationProfile {
+    /// The provider of the model.
+    pub provider: Provider,
+    /// The encoding whose counts are scaled.
+    pub tokenizer: Tokenizer,
+    /// Estimated number of tokens of the model per token of `tokenizer`.
+    pub correction_factor: f64,
+    /// The context size of the model.
+    pub context_size: usize,
+    /// The maximum number of output tokens of the model, or `None` if only the context size
+    /// limits the output.
+    pub max_output_tokens: Option<usize>,
+}
+
+impl EstimationProfile {
+    /// Creates a profile.
+    pub const fn new(
+        provider: Provider,
+        tokenizer: Tokenizer,
+        correction_factor: f64,
+        context_size: usize,
+        max_output_tokens: Option<usize>,
+    ) -> Self {
+        Self {
+            provider,
+            tokenizer,
+            correction_factor,
+            context_size,
+            max_output_tokens,
+        }
+    }
+
+    /// Scales a count of `tokenizer` tokens to an estimate for the model, rounding up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tiktoken_rs::model::get_estimation_profile;
+    /// let profile = get_estimation_profile("claude-sonnet-4-5").unwrap();
+    /// assert_eq!(profile.estimate(100), 120);
+    /// ```
+    pub fn estimate(&self, num_tokens: usize) -> usize {
+        // 50 * 1.1 is 55.000000000000007, which must not round up to 56. The rounding error of
+        // the product is relative to its size, so a few ulps are subtracted before rounding up.
+        let scaled = num_tokens as f64 * self.correction_factor;
+        (scaled - scaled.abs() * f64::EPSILON * 4.0).ceil().max(0.0) as usize
+    }
+
+    /// Estimates the number of tokens of a text for the model.
+    pub fn estimate_text(&self, text: &str) -> usize {
+        self.estimate(crate::api::bpe_singleton(self.tokenizer).count_with_special_tokens(text))
+    }
+}
+
+/// Returns the token estimation profile of a model of another provider.
+///
+/// Models are matched by prefix, e.g. `claude-sonnet-4-5-20250929` matches `claude-sonnet-4`.
+/// OpenAI models have no profile: their tokenizers are exact, see
+/// [`get_tokenizer`](crate::tokenizer::get_tokenizer).
+///
+/// # Arguments
+///
+/// * `model` - A string slice that holds the name of the model.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::model::{get_estimation_profile, Provider};
+/// let profile = get_estimation_profile("gemini-2.5-pro").unwrap();
+/// assert_eq!(profile.provider, Provider::Google);
+/// assert_eq!(profile.context_size, 1_048_576);
+/// assert_eq!(get_estimation_profile("gpt-4o"), None);
+/// ```
+///
+/// # Returns
+///
+/// Returns `None` if the model is not recognized.
+pub fn get_estimation_profile(model: &str) -> Option<EstimationProfile> {
+    use Provider::*;
+    use Tokenizer::{Cl100kBase, O200kBase};
+    let profile = EstimationProfile::new;
+
+    if starts_with_any!(model, "claude-opus-4-5", "claude-opus-4.5") {
+        return Some(profile(Anthropic, Cl100kBase, 1.2, 200_000, Some(64_000)));
+    }
+    if starts_with_any!(model, "claude-opus-4") {
+        return Some(profile(Anthropic, Cl100kBase, 1.2, 200_000, Some(32_000)));
+    }
+    if starts_with_any!(
+        model,
+        "claude-sonnet-4",
+        "claude-haiku-4",
+        "claude-3-7-sonnet"
+    ) {
+        return Some(profile(Anthropic, Cl100kBase, 1.2, 200_000, Some(64_000)));
+    }
+    if starts_with_any!(model, "claude-3-5") {
+        return Some(profile(Anthropic, Cl100kBase, 1.2, 200_000, Some(8_192)));
+    }
+    if starts_with_any!(model, "claude-3") {
+        return Some(profile(Anthropic, Cl100kBase, 1.2, 200_000, Some(4_096)));
+    }
+    if starts_with_any!(model, "gemini-2.5") {
+        return Some(profile(Google, O200kBase, 1.05, 1_048_576, Some(65_536)));
+    }
+    if starts_with_any!(model, "gemini-2.0", "gemini-1.5-flash") {
+        return Some(profile(Google, O200kBase, 1.05, 1_048_576, Some(8_192)));
+    }
+    if starts_with_any!(model, "gemini-1.5-pro") {
+        return Some(profile(Google, O200kBase, 1.05, 2_097_152, Some(8_192)));
+    }
+    if starts_with_any!(model, "llama-3.1", "llama-3.2", "llama-3.3") {
+        return Some(profile(Meta, Cl100kBase, 1.0, 131_072, None));
+    }
+    if starts_with_any!(model, "llama-3") {
+        return Some(profile(Meta, Cl100kBase, 1.0, 8_192, None));
+    }
+    if starts_with_any!(model, "codestral") {
+        return Some(profile(Mistral, Cl100kBase, 1.1, 262_144, None));
+    }
+    if starts_with_any!(
+        model,
+        "mistral-large",
+        "mistral-medium",
+        "mistral-small",
+        "mistral-nemo",
+        "open-mistral-nemo"
+    ) {
+        return Some(profile(Mistral, Cl100kBase, 1.1, 131_072, None));
+    }
+    if starts_with_any!(model, "deepseek-reasoner") {
+        return Some(profile(DeepSeek, Cl100kBase, 1.05, 131_072, Some(65_536)));
+    }
+    if starts_with_any!(model, "deepseek-chat") {
+        return Some(profile(DeepSeek, Cl100kBase, 1.05, 131_072, Some(8_192)));
+    }
+    None
+}
+
+/// Deprecation information about a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModelDeprecation {
+    /// The date (`YYYY-MM-DD`) on which the model was or will be shut down.
+    pub shutdown_date: &'static str,
+    /// The model OpenAI recommends using instead.
+    pub replacement: &'static str,
+}
+
+const fn deprecated(shutdown_date: &'static str, replacement: &'static str) -> ModelDeprecation {
+    ModelDeprecation {
+        shutdown_date,
+        replacement,
+    }
+}
+
+/// Returns deprecation information about a specified model.
+///
+/// Fine-tuned models (`ft:` prefix) report the deprecation of their base model.
+/// See <https://platform.openai.com/docs/deprecations> for up-to-date information.
+///
+/// # Arguments
+///
+/// * `model` - A string slice that holds the name of the model.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::model::get_model_deprecation;
+/// let deprecation = get_model_deprecation("text-davinci-003").unwrap();
+/// assert_eq!(deprecation.replacement, "gpt-3.5-turbo-instruct");
+/// assert_eq!(get_model_deprecation("gpt-4o"), None);
+/// ```
+///
+/// # Returns
+///
+/// Returns `None` if the model is not known to be deprecated.
+pub fn get_model_deprecation(model: &str) -> Option<ModelDeprecation> {
+    if let Some(rest) = model.strip_prefix("ft:") {
+        let base = rest.split(':').next().unwrap_or(rest);
+        return get_model_deprecation(base);
+    }
+    if starts_with_any!(model, "codex-mini") {
+        return Some(deprecated("2026-02-12", "gpt-5-codex-mini"));
+    }
+    if starts_with_any!(model, "chatgpt-4o") {
+        return Some(deprecated("2026-02-17", "gpt-5.1-chat-latest"));
+    }
+    if starts_with_any!(model, "o1-mini") {
+        return Some(deprecated("2025-10-27", "o4-mini"));
+    }
+    if starts_with_any!(model, "o1-preview") {
+        return Some(deprecated("2025-07-28", "o3"));
+    }
+    if starts_with_any!(model, "gpt-4.5-preview") {
+        return Some(deprecated("2025-07-14", "gpt-4.1"));
+    }
+    if starts_with_any!(model, "gpt-4-vision", "gpt-4-1106-vision") {
+        return Some(deprecated("2024-12-06", "gpt-4o"));
+    }
+    if starts_with_any!(model, "gpt-4-32k", "gpt-4-0314") {
+        return Some(deprecated("2025-06-06", "gpt-4o"));
+    }
+    if starts_with_any!(
+        model,
+        "gpt-3.5-turbo-0301",
+        "gpt-3.5-turbo-0613",
+        "gpt-3.5-turbo-16k-0613",
+    ) {
+        return Some(deprecated("2024-09-13", "gpt-3.5-turbo"));
+    }
+    if starts_with_any!(model, "text-davinci-edit", "code-davinci-edit") {
+        return Some(deprecated("2024-01-04", "gpt-4o"));
+    }
+    if starts_with_any!(
+        model,
+        "text-davinci-",
+        "text-curie-",
+        "text-babbage-",
+        "text-ada-"
+    ) {
+        return Some(deprecated("2024-01-04", "gpt-3.5-turbo-instruct"));
+    }
+    if starts_with_any!(
+        model,
+        "code-davinci-",
+        "code-cushman-",
+        "davinci-codex",
+        "cushman-codex"
+    ) {
+        return Some(deprecated("2023-03-23", "gpt-4o"));
+    }
+    if starts_with_any!(model, "text-similarity-", "text-search-", "code-search-") {
+        return Some(deprecated("2024-01-04", "text-embedding-3-small"));
+    }
+    if matches!(model, "davinci" | "curie") {
+        return Some(deprecated("2024-01-04", "davinci-002"));
+    }
+    if matches!(model, "babbage" | "ada") {
+        return Some(deprecated("2024-01-04", "babbage-002"));
+    }
+    None
+}
+
+type DeprecationHook = Arc<dyn Fn(&str, &ModelDeprecation) + Send + Sync>;
+
+static DEPRECATION_HOOK: RwLock<Option<DeprecationHook>> = RwLock::new(None);
+
+/// Deprecated models reported since the hook was last set or cleared.
+static REPORTED_MODELS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Registers a callback invoked when a deprecated model is resolved.
+///
+/// The callback receives the model name as given by the caller and its [`ModelDeprecation`].
+/// It is called by [`bpe_for_model`](crate::bpe_for_model) and the chat token counting
+/// functions such as [`num_tokens_from_messages`](crate::num_tokens_from_messages), once per
+/// model: later lookups of the same model are not reported again.
+/// Registering a new callback replaces the previous one, and reports each model again.
+/// The callback is not run under a lock, so it may itself replace or clear the callback.
+///
+/// With the `log` feature enabled, a warning is also emitted through the `log` crate,
+/// whether or not a callback is registered.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::model::set_deprecation_hook;
+///
+/// set_deprecation_hook(|model, deprecation| {
+///     eprintln!(
+///         "warning: {model} is deprecated (shutdown {}), use {} instead",
+///         deprecation.shutdown_date, deprecation.replacement
+///     );
+/// });
+/// ```
+pub fn set_deprecation_hook<F>(hook: F)
+where
+    F: Fn(&str, &ModelDeprecation) + Send + Sync + 'static,
+{
+    *DEPRECATION_HOOK.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(hook));
+    reported_models().clear();
+}
+
+/// Removes the callback registered with [`set_deprecation_hook`].
+pub fn clear_deprecation_hook() {
+    *DEPRECATION_HOOK.write().unwrap_or_else(|e| e.into_inner()) = None;
+    reported_models().clear();
+}
+
+fn reported_models() -> std::sync::MutexGuard<'static, BTreeSet<String>> {
+    REPORTED_MODELS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Reports `model` to the deprecation hook and the `log` crate if it is deprecated and has
+/// not been reported yet.
+pub(crate) fn notify_if_deprecated(model: &str) {
+    let Some(deprecation) = get_model_deprecation(model) else {
+        return;
+    };
+    if !reported_models().insert(model.to_string()) {
+        return;
+    }
+    #[cfg(feature = "log")]
+    log::warn!(
+        "model {} is deprecated and shuts down on {}; use {} instead",
+        model,
+        deprecation.shutdown_date,
+        deprecation.replacement
+    );
+    // The hook is called without holding the lock, so that it can replace or clear itself
+    let hook = DEPRECATION_HOOK
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    if let Some(hook) = hook {
+        hook(model, &deprecation);
+    }
+}
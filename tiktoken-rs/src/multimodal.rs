@@ -0,0 +1,492 @@
+/*!
+ * estimates tokens for non-text inputs such as images, audio and files.
+ *
+ * These inputs are not BPE encoded; the API bills them with per-model formulas based on
+ * their size. The estimators here take the size as input, so no network access, image
+ * decoding or PDF parsing is needed.
+ */
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+
+use crate::bpe_for_model;
+use crate::model::{get_audio_tokens_per_second, get_image_token_cost, ImageTokenCost};
+
+/// The `detail` setting of an image input.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageDetail {
+    /// Let the API choose. Estimated as [`ImageDetail::High`], which gives an upper bound.
+    #[default]
+    Auto,
+    /// A fixed, low resolution version of the image.
+    Low,
+    /// The full resolution image, cut into tiles.
+    High,
+}
+
+/// An image input, described by its dimensions in pixels.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageInput {
+    /// Width of the image in pixels.
+    pub width: u32,
+    /// Height of the image in pixels.
+    pub height: u32,
+    /// The requested level of detail.
+    pub detail: ImageDetail,
+}
+
+impl ImageInput {
+    /// Creates an image input with the default (`auto`) detail.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            detail: ImageDetail::Auto,
+        }
+    }
+
+    /// Creates an image input for an image of unknown dimensions. It is sized at 2048x768,
+    /// the largest image after resizing, so its estimate is an upper bound for any image with
+    /// the given detail.
+    pub fn unknown_size(detail: ImageDetail) -> Self {
+        Self {
+            width: 2048,
+            height: 768,
+            detail,
+        }
+    }
+}
+
+/// Returns the estimated number of tokens for an image input.
+///
+/// See <https://platform.openai.com/docs/guides/images-vision#calculating-costs> for the formulas.
+///
+/// # Arguments
+///
+/// * `model` - A string slice representing the model name, e.g., `"gpt-4o"`.
+/// * `image` - The dimensions and detail of the image.
+///
+/// # Errors
+///
+/// Returns an error if the model is not a known vision model.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::multimodal::{num_tokens_from_image, ImageDetail, ImageInput};
+///
+/// let image = ImageInput { width: 1024, height: 1024, detail: ImageDetail::High };
+/// assert_eq!(num_tokens_from_image("gpt-4o", &image).unwrap(), 765);
+///
+/// let image = ImageInput { detail: ImageDetail::Low, ..image };
+/// assert_eq!(num_tokens_from_image("gpt-4o", &image).unwrap(), 85);
+/// ```
+pub fn num_tokens_from_image(model: &str, image: &ImageInput) -> Result<usize> {
+    let cost = get_image_token_cost(model)
+        .ok_or_else(|| anyhow!("Model {} does not accept image inputs", model))?;
+    Ok(count_image_tokens(cost, image))
+}
+
+pub(crate) fn count_image_tokens(cost: ImageTokenCost, image: &ImageInput) -> usize {
+    match cost {
+        ImageTokenCost::Tiles {
+            base_tokens,
+            tile_tokens,
+        } => match image.detail {
+            ImageDetail::Low => base_tokens,
+            ImageDetail::High | ImageDetail::Auto => {
+                base_tokens + tile_tokens * count_tiles(image.width, image.height)
+            }
+        },
+        ImageTokenCost::Patches { multiplier } => {
+            ceil(count_patches(image.width, image.height) as f64 * multiplier)
+        }
+    }
+}
+
+/// An audio input, described by its duration.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AudioInput {
+    /// Length of the audio clip.
+    pub duration: Duration,
+}
+
+impl AudioInput {
+    /// Creates an audio input of the given duration.
+    pub fn new(duration: Duration) -> Self {
+        Self { duration }
+    }
+
+    /// Creates an audio input from the bytes of a WAV file, whose duration is read from its
+    /// header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not a WAV file with a `fmt ` and a `data` chunk.
+    pub fn from_wav(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            anyhow::bail!("Audio is not a WAV file")
+        }
+        let u32_at = |at: usize| {
+            let le: [u8; 4] = bytes[at..at + 4].try_into().unwrap();
+            u32::from_le_bytes(le) as usize
+        };
+        let mut byte_rate = None;
+        let mut offset = 12;
+        while offset + 8 <= bytes.len() {
+            let (id, size) = (&bytes[offset..offset + 4], u32_at(offset + 4));
+            let body = offset + 8;
+            match id {
+                b"fmt " if size >= 12 && body + 12 <= bytes.len() => {
+                    byte_rate = Some(u32_at(body + 8));
+                }
+                b"data" => {
+                    let byte_rate = byte_rate
+                        .filter(|&rate| rate > 0)
+                        .ok_or_else(|| anyhow!("WAV file has no format before its data"))?;
+                    // Streamed files may give a placeholder size, so the data ends at the
+                    // end of the file at the latest
+                    let size = size.min(bytes.len() - body);
+                    return Ok(Self::new(Duration::from_secs_f64(
+                        size as f64 / byte_rate as f64,
+                    )));
+                }
+                _ => {}
+            }
+            // Chunks are padded to an even size
+            offset = body.saturating_add(size).saturating_add(size % 2);
+        }
+        anyhow::bail!("WAV file has no data")
+    }
+}
+
+/// Returns the estimated number of tokens for an audio input.
+///
+/// # Arguments
+///
+/// * `model` - A string slice representing the model name, e.g., `"gpt-4o-audio-preview"`.
+/// * `audio` - The duration of the audio clip.
+///
+/// # Errors
+///
+/// Returns an error if the model is not a known audio model.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use tiktoken_rs::multimodal::{num_tokens_from_audio, AudioInput};
+///
+/// let audio = AudioInput::new(Duration::from_secs(30));
+/// assert_eq!(num_tokens_from_audio("gpt-4o-audio-preview", &audio).unwrap(), 300);
+/// ```
+pub fn num_tokens_from_audio(model: &str, audio: &AudioInput) -> Result<usize> {
+    let tokens_per_second = get_audio_tokens_per_second(model)
+        .ok_or_else(|| anyhow!("Model {} does not accept audio inputs", model))?;
+    Ok(count_audio_tokens(tokens_per_second, audio))
+}
+
+pub(crate) fn count_audio_tokens(tokens_per_second: f64, audio: &AudioInput) -> usize {
+    ceil(audio.duration.as_secs_f64() * tokens_per_second)
+}
+
+/// A file input such as a PDF, described by its extracted text and number of pages.
+///
+/// The API gives the model both the text extracted from each page and an image of each page,
+/// so both are billed. Extracting the text is left to the caller.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct FileInput {
+    /// The text extracted from the file.
+    pub text: String,
+    /// Number of page images sent alongside the text, usually one per page.
+    pub page_images: usize,
+    /// The size of each page image.
+    pub page_image: ImageInput,
+}
+
+impl FileInput {
+    /// Creates a file input whose pages are rendered as US Letter sized images (612x792).
+    pub fn new(text: impl Into<String>, page_images: usize) -> Self {
+        Self {
+            text: text.into(),
+            page_images,
+            page_image: ImageInput::new(612, 792),
+        }
+    }
+}
+
+/// Returns the estimated number of tokens for a file input.
+///
+/// # Arguments
+///
+/// * `model` - A string slice representing the model name, e.g., `"gpt-4o"`.
+/// * `file` - The extracted text and page images of the file.
+///
+/// # Errors
+///
+/// Returns an error if no tokenizer is found for the model, or if the file has page images
+/// and the model is not a known vision model.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::multimodal::{num_tokens_from_file, FileInput};
+///
+/// let text_only = FileInput::new("Quarterly report", 0);
+/// let with_pages = FileInput::new("Quarterly report", 2);
+/// let text_tokens = num_tokens_from_file("gpt-4o", &text_only).unwrap();
+/// assert_eq!(
+///     num_tokens_from_file("gpt-4o", &with_pages).unwrap(),
+///     text_tokens + 2 * 765
+/// );
+/// ```
+pub fn num_tokens_from_file(model: &str, file: &FileInput) -> Result<usize> {
+    let text_tokens = bpe_for_model(model)?.count_with_special_tokens(&file.text);
+    DefaultEstimator.file_tokens(model, file, text_tokens)
+}
+
+/// Estimates the tokens of the non-text content parts of chat messages.
+///
+/// Each method defaults to the built-in formula ([`num_tokens_from_image`],
+/// [`num_tokens_from_audio`] and [`num_tokens_from_file`]), so an implementation only needs to
+/// override the parts it estimates differently, e.g. with measured usage or a provider's own
+/// formula.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use tiktoken_rs::multimodal::{ContentPartEstimator, ImageInput};
+/// use tiktoken_rs::{num_tokens_from_messages_with_estimator, ChatCompletionRequestMessage, ContentPart};
+///
+/// /// Bills every image at a flat rate.
+/// struct FlatImages;
+///
+/// impl ContentPartEstimator for FlatImages {
+///     fn image_tokens(&self, _model: &str, _image: &ImageInput) -> Result<usize> {
+///         Ok(1_000)
+///     }
+/// }
+///
+/// let text = ChatCompletionRequestMessage {
+///     role: "user".to_string(),
+///     content: Some("What is this?".into()),
+///     ..Default::default()
+/// };
+/// let with_image = ChatCompletionRequestMessage {
+///     content: Some(vec!["What is this?".into(), ContentPart::Image(ImageInput::new(512, 512))].into()),
+///     ..text.clone()
+/// };
+/// let text_tokens = num_tokens_from_messages_with_estimator("gpt-4o", &[text], &FlatImages).unwrap();
+/// let image_tokens =
+///     num_tokens_from_messages_with_estimator("gpt-4o", &[with_image], &FlatImages).unwrap();
+/// assert_eq!(image_tokens, text_tokens + 1_000);
+/// ```
+pub trait ContentPartEstimator {
+    /// Returns the number of tokens of an image.
+    fn image_tokens(&self, model: &str, image: &ImageInput) -> Result<usize> {
+        num_tokens_from_image(model, image)
+    }
+
+    /// Returns the number of tokens of an audio clip.
+    fn audio_tokens(&self, model: &str, audio: &AudioInput) -> Result<usize> {
+        num_tokens_from_audio(model, audio)
+    }
+
+    /// Returns the number of tokens of a file, given the number of tokens of its text.
+    ///
+    /// The text is counted by the caller, with the same tokenizer as the rest of the
+    /// messages. The page images default to [`ContentPartEstimator::image_tokens`] each.
+    fn file_tokens(&self, model: &str, file: &FileInput, text_tokens: usize) -> Result<usize> {
+        if file.page_images == 0 {
+            return Ok(text_tokens);
+        }
+        Ok(text_tokens + file.page_images * self.image_tokens(model, &file.page_image)?)
+    }
+}
+
+/// The built-in estimator, used by [`num_tokens_from_messages`](crate::num_tokens_from_messages).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DefaultEstimator;
+
+impl ContentPartEstimator for DefaultEstimator {}
+
+/// Rounds up, ignoring floating point noise from the resizing arithmetic.
+fn ceil(x: f64) -> usize {
+    (x - 1e-9).ceil().max(0.0) as usize
+}
+
+/// Number of 512px tiles for a high detail image: the image is first scaled to fit in a
+/// 2048x2048 square, then scaled down so that its shortest side is at most 768px.
+fn count_tiles(width: u32, height: u32) -> usize {
+    const MAX_SIDE: f64 = 2048.0;
+    const SHORT_SIDE: f64 = 768.0;
+    const TILE_SIZE: f64 = 512.0;
+
+    let (mut width, mut height) = (width as f64, height as f64);
+    if width > MAX_SIDE || height > MAX_SIDE {
+        let scale = MAX_SIDE / width.max(height);
+        width *= scale;
+        height *= scale;
+    }
+    if width.min(height) > SHORT_SIDE {
+        let scale = SHORT_SIDE / width.min(height);
+        width *= scale;
+        height *= scale;
+    }
+    ceil(width / TILE_SIZE) * ceil(height / TILE_SIZE)
+}
+
+/// Number of 32px patches needed to cover the image, after scaling it down so that it fits in
+/// at most 1536 patches.
+fn count_patches(width: u32, height: u32) -> usize {
+    const PATCH_SIZE: f64 = 32.0;
+    const MAX_PATCHES: usize = 1536;
+
+    let (width, height) = (width as f64, height as f64);
+    let patches = ceil(width / PATCH_SIZE) * ceil(height / PATCH_SIZE);
+    if patches <= MAX_PATCHES {
+        return patches;
+    }
+    let scale = (PATCH_SIZE * PATCH_SIZE * MAX_PATCHES as f64 / (width * height)).sqrt();
+    let (patches_wide, patches_high) = (width * scale / PATCH_SIZE, height * scale / PATCH_SIZE);
+    let shrink = (patches_wide.floor() / patches_wide).min(patches_high.floor() / patches_high);
+    let patches = ceil(patches_wide * shrink) * ceil(patches_high * shrink);
+    patches.min(MAX_PATCHES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn high(width: u32, height: u32) -> ImageInput {
+        ImageInput {
+            width,
+            height,
+            detail: ImageDetail::High,
+        }
+    }
+
+    #[test]
+    fn test_tile_based_images() {
+        // Examples from the OpenAI vision guide
+        assert_eq!(
+            num_tokens_from_image("gpt-4o", &high(1024, 1024)).unwrap(),
+            765
+        );
+        assert_eq!(
+            num_tokens_from_image("gpt-4o", &high(2048, 4096)).unwrap(),
+            1105
+        );
+        assert_eq!(
+            num_tokens_from_image("gpt-4o", &high(256, 256)).unwrap(),
+            255
+        );
+        assert_eq!(
+            num_tokens_from_image("gpt-4o-mini", &high(1024, 1024)).unwrap(),
+            2833 + 4 * 5667
+        );
+        assert_eq!(
+            num_tokens_from_image("o1", &ImageInput::new(1024, 1024)).unwrap(),
+            75 + 4 * 150
+        );
+    }
+
+    #[test]
+    fn test_patch_based_images() {
+        // Examples from the OpenAI vision guide: 1024x1024 is 1024 patches, 1800x2400 is
+        // resized to 1452 patches
+        assert_eq!(count_patches(1024, 1024), 1024);
+        assert_eq!(count_patches(1800, 2400), 1452);
+        assert_eq!(count_patches(100, 100), 16);
+        assert_eq!(
+            num_tokens_from_image("gpt-4.1-mini", &high(1024, 1024)).unwrap(),
+            1659
+        );
+        assert_eq!(
+            num_tokens_from_image("o4-mini", &high(1800, 2400)).unwrap(),
+            (1452.0_f64 * 1.72).ceil() as usize
+        );
+    }
+
+    #[test]
+    fn test_audio() {
+        let audio = |millis| AudioInput::new(Duration::from_millis(millis));
+        assert_eq!(
+            num_tokens_from_audio("gpt-4o-audio-preview", &audio(0)).unwrap(),
+            0
+        );
+        assert_eq!(
+            num_tokens_from_audio("gpt-audio", &audio(1000)).unwrap(),
+            10
+        );
+        // Partial tenths of a second are rounded up
+        assert_eq!(
+            num_tokens_from_audio("gpt-4o-mini-audio-preview", &audio(1250)).unwrap(),
+            13
+        );
+        assert!(num_tokens_from_audio("gpt-4o", &audio(1000)).is_err());
+    }
+
+    #[test]
+    fn test_audio_from_wav() {
+        // 16 kHz mono 16-bit PCM, i.e. 32000 bytes per second
+        let wav = |data_size: u32, data: usize| {
+            let mut bytes = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+            bytes.extend(16u32.to_le_bytes());
+            bytes.extend([1, 0, 1, 0]);
+            bytes.extend(16_000u32.to_le_bytes());
+            bytes.extend(32_000u32.to_le_bytes());
+            bytes.extend([2, 0, 16, 0]);
+            bytes.extend(b"LIST");
+            bytes.extend(3u32.to_le_bytes());
+            bytes.extend([0; 4]);
+            bytes.extend(b"data");
+            bytes.extend(data_size.to_le_bytes());
+            bytes.extend(vec![0; data]);
+            bytes
+        };
+        let duration = |bytes: &[u8]| AudioInput::from_wav(bytes).unwrap().duration;
+        assert_eq!(duration(&wav(48_000, 48_000)), Duration::from_millis(1500));
+        // Placeholder size of a streamed file
+        assert_eq!(duration(&wav(u32::MAX, 16_000)), Duration::from_millis(500));
+        assert!(AudioInput::from_wav(b"ID3\x03").is_err());
+        assert!(AudioInput::from_wav(&wav(0, 0)[..36]).is_err());
+    }
+
+    #[test]
+    fn test_file() {
+        let bpe = bpe_for_model("gpt-4o").unwrap();
+        let file = FileInput {
+            text: "Page one.\nPage two.".to_string(),
+            page_images: 2,
+            page_image: ImageInput::new(1024, 1024),
+        };
+        assert_eq!(
+            num_tokens_from_file("gpt-4o", &file).unwrap(),
+            bpe.count_with_special_tokens(&file.text) + 2 * 765
+        );
+        // Text only files work with any chat model, page images need vision
+        let text_only = FileInput::new("Hello", 0);
+        assert!(num_tokens_from_file("gpt-3.5-turbo", &text_only).is_ok());
+        assert!(num_tokens_from_file("gpt-3.5-turbo", &file).is_err());
+    }
+
+    #[test]
+    fn test_unknown_size_is_an_upper_bound() {
+        for model in ["gpt-4o", "gpt-4.1-mini"] {
+            let bound =
+                num_tokens_from_image(model, &ImageInput::unknown_size(ImageDetail::High)).unwrap();
+            for (width, height) in [(2048, 2048), (4096, 1024), (768, 2048), (1000, 1500)] {
+                assert!(num_tokens_from_image(model, &high(width, height)).unwrap() <= bound);
+            }
+        }
+    }
+
+    #[test]
+    fn test_non_vision_model() {
+        assert!(num_tokens_from_image("o3-mini", &high(512, 512)).is_err());
+        assert!(num_tokens_from_image("gpt-3.5-turbo", &high(512, 512)).is_err());
+    }
+}
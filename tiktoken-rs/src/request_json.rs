@@ -0,0 +1,653 @@
+/*!
+ * counts tokens directly from the JSON body of a Chat Completions or Responses API request.
+ *
+ * This does not depend on the types of any SDK: the body is read as a [`serde_json::Value`],
+ * converted into a [`ChatCompletionRequest`] or [`ResponseRequest`] and counted like them.
+ * A body with `messages` is a Chat Completions request, and a body with `input` is a
+ * Responses request.
+ *
+ * The body carries no image dimensions, so images are estimated at the highest cost of their
+ * `detail` (see [`ImageInput::unknown_size`](crate::multimodal::ImageInput::unknown_size)).
+ * The duration of wav audio is read from its data. File inputs and audio in other formats
+ * cannot be estimated and are rejected, rather than undercounted, and so are Responses input
+ * items that cannot be counted: item references, whose content is not in the body, and the
+ * calls and outputs of built-in tools. The definitions of built-in tools such as web search
+ * are not counted.
+ */
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::counter::TokenCounter;
+use crate::model::get_context_size;
+use crate::responses::{ResponseInputItem, ResponseRequest};
+use crate::tools::{FunctionDefinition, JsonSchemaFormat, ResponseFormat};
+use crate::wire::{audio_part, file_part_error, image_part};
+use crate::{
+    ChatCompletionRequest, ChatCompletionRequestMessage, ChatCounter, ContentPart, MessageContent,
+    ToolChoice,
+};
+
+/// The token count of a request body, as returned by [`num_tokens_from_request_json`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestJsonTokens {
+    /// The `model` of the request.
+    pub model: String,
+    /// Number of tokens in the prompt.
+    pub prompt_tokens: usize,
+    /// The context size of the model.
+    pub context_size: usize,
+    /// Number of tokens left in the context window for the completion.
+    pub remaining_tokens: usize,
+    /// The completion limit set by the request (`max_completion_tokens`, `max_tokens` or
+    /// `max_output_tokens`), if any.
+    pub max_completion_tokens: Option<usize>,
+}
+
+/// Counts the prompt tokens of a Chat Completions or Responses API request body.
+///
+/// # Arguments
+///
+/// * `body` - The JSON body of the request. It must have a `model` and either `messages`
+///   (Chat Completions) or `input` (Responses).
+///
+/// # Errors
+///
+/// Returns an error if the body is not a request, if the context size of its model is unknown,
+/// or if the request cannot be counted for the model
+/// (see [`num_tokens_from_messages`](crate::num_tokens_from_messages)).
+///
+/// # Examples
+///
+/// ```
+/// use serde_json::json;
+/// use tiktoken_rs::request_json::num_tokens_from_request_json;
+///
+/// let body = json!({
+///     "model": "gpt-4o",
+///     "messages": [{ "role": "user", "content": "Hello!" }],
+///     "max_completion_tokens": 256
+/// });
+/// let tokens = num_tokens_from_request_json(&body).unwrap();
+/// assert_eq!(tokens.prompt_tokens + tokens.remaining_tokens, 128_000);
+/// assert_eq!(tokens.max_completion_tokens, Some(256));
+/// ```
+pub fn num_tokens_from_request_json(body: &Value) -> Result<RequestJsonTokens> {
+    ChatCounter::new(request_model(body)?)?.num_tokens_from_request_json(body)
+}
+
+fn request_model(body: &Value) -> Result<&str> {
+    body.get("model")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("Request has no model"))
+}
+
+impl<C: TokenCounter + ?Sized> ChatCounter<'_, C> {
+    /// Counts the prompt tokens of a request body, see [`num_tokens_from_request_json`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `model` of the body is not the model of the counter, or the
+    /// errors of [`num_tokens_from_request_json`].
+    pub fn num_tokens_from_request_json(&self, body: &Value) -> Result<RequestJsonTokens> {
+        let model = request_model(body)?;
+        if model != self.model() {
+            anyhow::bail!(
+                "Request is for model {}, but the counter counts for model {}",
+                model,
+                self.model()
+            )
+        }
+        let context_size = get_context_size(model)
+            .ok_or_else(|| anyhow!("Unknown context size for model {}", model))?;
+        let prompt_tokens = if body.get("messages").is_some() {
+            self.num_tokens_from_request(&chat_request_from_json(body)?)?
+        } else if body.get("input").is_some() {
+            self.num_tokens_from_response_request(&response_request_from_json(body)?)?
+        } else {
+            anyhow::bail!("Request has neither messages nor input")
+        };
+        let max_completion_tokens = ["max_completion_tokens", "max_tokens", "max_output_tokens"]
+            .iter()
+            .find_map(|key| body.get(*key).and_then(Value::as_u64))
+            .map(|n| n as usize);
+        Ok(RequestJsonTokens {
+            model: model.to_string(),
+            prompt_tokens,
+            context_size,
+            remaining_tokens: context_size.saturating_sub(prompt_tokens),
+            max_completion_tokens,
+        })
+    }
+}
+
+/// Counts the prompt tokens of a request body given as a JSON string.
+///
+/// # Errors
+///
+/// Returns an error if the body is not valid JSON, or if it cannot be counted
+/// (see [`num_tokens_from_request_json`]).
+pub fn num_tokens_from_request_json_str(body: &str) -> Result<RequestJsonTokens> {
+    let body: Value = serde_json::from_str(body).context("Request body is not valid JSON")?;
+    num_tokens_from_request_json(&body)
+}
+
+/// Reads the `messages`, `tools` (or legacy `functions`), `tool_choice` (or legacy
+/// `function_call`) and `response_format` of a Chat Completions request body.
+///
+/// # Errors
+///
+/// Returns an error if the messages are not in the OpenAI wire format.
+pub fn chat_request_from_json(body: &Value) -> Result<ChatCompletionRequest> {
+    let messages = match body.get("messages") {
+        Some(messages) => Vec::<ChatCompletionRequestMessage>::deserialize(messages)
+            .context("Invalid messages in request")?,
+        None => Vec::new(),
+    };
+    let tools = match (body.get("tools"), body.get("functions")) {
+        (Some(Value::Array(tools)), _) => tools
+            .iter()
+            .filter_map(|tool| match tool.get("type").and_then(Value::as_str)? {
+                kind @ ("function" | "custom") => tool.get(kind).and_then(function_definition),
+                _ => None,
+            })
+            .collect(),
+        (_, Some(Value::Array(functions))) => {
+            functions.iter().filter_map(function_definition).collect()
+        }
+        _ => Vec::new(),
+    };
+    let tool_choice = body
+        .get("tool_choice")
+        .or_else(|| body.get("function_call"))
+        .and_then(|choice| {
+            tool_choice(choice, |choice| {
+                choice
+                    .get("function")
+                    .or_else(|| choice.get("custom"))
+                    .unwrap_or(choice)
+                    .get("name")
+                    .and_then(Value::as_str)
+            })
+        });
+    let response_format = body
+        .get("response_format")
+        .and_then(|format| response_format(format, format.get("json_schema")));
+    Ok(ChatCompletionRequest {
+        messages,
+        tools,
+        tool_choice,
+        response_format,
+    })
+}
+
+/// Reads the `instructions`, `input`, `tools`, `tool_choice` and `text.format` of a Responses
+/// request body.
+///
+/// # Errors
+///
+/// Returns an error if `input` is neither a string nor an array, or if it has an item that
+/// cannot be counted: a message without a role, an item reference, an item of a built-in tool,
+/// or a file or an audio clip that cannot be estimated.
+pub fn response_request_from_json(body: &Value) -> Result<ResponseRequest> {
+    let input = match body.get("input") {
+        Some(Value::String(text)) => vec![ResponseInputItem::message("user", text.clone())],
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(response_input_item)
+            .collect::<Result<_>>()?,
+        Some(_) => anyhow::bail!("Request input is neither a string nor an array"),
+        None => Vec::new(),
+    };
+    let tools = body
+        .get("tools")
+        .and_then(Value::as_array)
+        .map(|tools| {
+            tools
+                .iter()
+                .filter(|tool| {
+                    matches!(
+                        tool.get("type").and_then(Value::as_str),
+                        Some("function" | "custom")
+                    )
+                })
+                .filter_map(function_definition)
+                .collect()
+        })
+        .unwrap_or_default();
+    let tool_choice = body
+        .get("tool_choice")
+        .and_then(|choice| tool_choice(choice, |choice| choice.get("name")?.as_str()));
+    Ok(ResponseRequest {
+        instructions: body
+            .get("instructions")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        input,
+        tools,
+        tool_choice,
+        response_format: body
+            .pointer("/text/format")
+            .and_then(|format| response_format(format, Some(format))),
+    })
+}
+
+fn function_definition(function: &Value) -> Option<FunctionDefinition> {
+    Some(FunctionDefinition {
+        name: function.get("name")?.as_str()?.to_string(),
+        description: function
+            .get("description")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        parameters: function.get("parameters").filter(|p| !p.is_null()).cloned(),
+    })
+}
+
+fn tool_choice<'a>(
+    choice: &'a Value,
+    function_name: impl Fn(&'a Value) -> Option<&'a str>,
+) -> Option<ToolChoice> {
+    match choice {
+        Value::String(mode) => match mode.as_str() {
+            "none" => Some(ToolChoice::None),
+            "auto" => Some(ToolChoice::Auto),
+            "required" => Some(ToolChoice::Required),
+            _ => None,
+        },
+        _ => function_name(choice).map(|name| ToolChoice::Function(name.to_string())),
+    }
+}
+
+/// Reads a response format, whose JSON schema settings are in `json_schema` (Chat Completions)
+/// or next to its `type` (Responses).
+fn response_format(format: &Value, json_schema: Option<&Value>) -> Option<ResponseFormat> {
+    match format.get("type")?.as_str()? {
+        "text" => Some(ResponseFormat::Text),
+        "json_object" => Some(ResponseFormat::JsonObject),
+        "json_schema" => {
+            let json_schema = json_schema?;
+            Some(ResponseFormat::JsonSchema(JsonSchemaFormat {
+                name: json_schema.get("name")?.as_str()?.to_string(),
+                description: json_schema
+                    .get("description")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                schema: json_schema.get("schema").filter(|s| !s.is_null()).cloned(),
+                strict: json_schema.get("strict").and_then(Value::as_bool),
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// Joins the text of a string or an array of content parts, or returns `None` if there is none.
+fn content_text(content: &Value, text_key: &str) -> Option<String> {
+    match content {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(parts) => {
+            let texts: Vec<&str> = parts
+                .iter()
+                .filter_map(|part| part.get(text_key)?.as_str())
+                .collect();
+            (!texts.is_empty()).then(|| texts.concat())
+        }
+        _ => None,
+    }
+}
+
+/// Reads the content of a Responses message: a string, or an array of content parts.
+fn message_content(content: &Value) -> Result<MessageContent> {
+    let parts = match content {
+        Value::String(text) => return Ok(MessageContent::Text(text.clone())),
+        Value::Array(parts) => parts,
+        _ => anyhow::bail!("Message content is neither a string nor an array"),
+    };
+    let mut content_parts = Vec::with_capacity(parts.len());
+    for part in parts {
+        let string = |key: &str| part.get(key).and_then(Value::as_str);
+        match string("type") {
+            Some("input_text" | "output_text" | "text") => {
+                content_parts.push(string("text").unwrap_or_default().into())
+            }
+            Some("refusal") => content_parts.push(ContentPart::Refusal(
+                string("refusal").unwrap_or_default().to_string(),
+            )),
+            Some("input_image") => content_parts.push(image_part(string("detail"))),
+            Some("input_audio") => {
+                let audio = part.get("input_audio");
+                let field = |key| audio?.get(key)?.as_str();
+                let (data, format) = field("data")
+                    .zip(field("format"))
+                    .ok_or_else(|| anyhow!("Invalid input_audio part in request"))?;
+                content_parts.push(audio_part(data, format)?)
+            }
+            Some("input_file") => return Err(file_part_error()),
+            kind => anyhow::bail!("Cannot count content parts of type {:?}", kind),
+        }
+    }
+    Ok(MessageContent::Parts(content_parts))
+}
+
+fn response_input_item(item: &Value) -> Result<ResponseInputItem> {
+    let string = |key: &str| Some(item.get(key)?.as_str()?.to_string());
+    let kind = item
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or("message");
+    let required = |key: &str| {
+        string(key).ok_or_else(|| anyhow!("Input item of type {} has no {}", kind, key))
+    };
+    match kind {
+        "message" => Ok(ResponseInputItem::Message(ChatCompletionRequestMessage {
+            role: required("role")?,
+            content: item.get("content").map(message_content).transpose()?,
+            ..Default::default()
+        })),
+        "function_call" => Ok(ResponseInputItem::FunctionCall {
+            call_id: string("call_id").unwrap_or_default(),
+            name: required("name")?,
+            arguments: string("arguments").unwrap_or_default(),
+        }),
+        "custom_tool_call" => Ok(ResponseInputItem::FunctionCall {
+            call_id: string("call_id").unwrap_or_default(),
+            name: required("name")?,
+            arguments: string("input").unwrap_or_default(),
+        }),
+        "function_call_output" | "custom_tool_call_output" => {
+            Ok(ResponseInputItem::FunctionCallOutput {
+                call_id: string("call_id").unwrap_or_default(),
+                output: item
+                    .get("output")
+                    .and_then(|output| content_text(output, "text"))
+                    .unwrap_or_default(),
+            })
+        }
+        "reasoning" => Ok(ResponseInputItem::Reasoning {
+            summary: item
+                .get("summary")
+                .and_then(Value::as_array)
+                .map(|parts| {
+                    parts
+                        .iter()
+                        .filter_map(|part| Some(part.get("text")?.as_str()?.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }),
+        "item_reference" => anyhow::bail!("Cannot count item references, their content is stored"),
+        _ => anyhow::bail!("Cannot count input items of type {}", kind),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::multimodal::ImageInput;
+    use crate::responses::num_tokens_from_response_request;
+    use crate::{num_tokens_from_messages, num_tokens_from_request};
+
+    #[test]
+    fn test_chat_request() {
+        let body = json!({
+            "model": "gpt-4o",
+            "messages": [
+                { "role": "system", "content": "You are a weather bot." },
+                { "role": "user", "content": [{ "type": "text", "text": "Weather in Paris?" }] }
+            ],
+            "tools": [{
+                "type": "function",
+                "function": {
+                    "name": "get_weather",
+                    "description": "Get the weather",
+                    "parameters": {
+                        "type": "object",
+                        "properties": { "city": { "type": "string" } }
+                    }
+                }
+            }],
+            "tool_choice": { "type": "function", "function": { "name": "get_weather" } },
+            "max_tokens": 100
+        });
+        let request = chat_request_from_json(&body).unwrap();
+        assert_eq!(request.tools[0].name, "get_weather");
+        assert_eq!(
+            request.tool_choice,
+            Some(ToolChoice::Function("get_weather".to_string()))
+        );
+        let tokens = num_tokens_from_request_json(&body).unwrap();
+        assert_eq!(
+            tokens.prompt_tokens,
+            num_tokens_from_request("gpt-4o", &request).unwrap()
+        );
+        assert!(
+            tokens.prompt_tokens > num_tokens_from_messages("gpt-4o", &request.messages).unwrap()
+        );
+        assert_eq!(tokens.max_completion_tokens, Some(100));
+        assert_eq!(
+            num_tokens_from_request_json_str(&body.to_string()).unwrap(),
+            tokens
+        );
+    }
+
+    #[test]
+    fn test_response_formats() {
+        let schema = json!({ "type": "object", "properties": { "a": { "type": "string" } } });
+        let expected = ResponseFormat::JsonSchema(JsonSchemaFormat {
+            name: "answer".to_string(),
+            schema: Some(schema.clone()),
+            strict: Some(true),
+            ..Default::default()
+        });
+        let chat = json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "user", "content": "Hi" }],
+            "response_format": {
+                "type": "json_schema",
+                "json_schema": { "name": "answer", "schema": schema, "strict": true }
+            }
+        });
+        let responses = json!({
+            "model": "gpt-4o",
+            "input": "Hi",
+            "text": {
+                "format": { "type": "json_schema", "name": "answer", "schema": schema, "strict": true }
+            }
+        });
+        assert_eq!(
+            chat_request_from_json(&chat).unwrap().response_format,
+            Some(expected.clone())
+        );
+        assert_eq!(
+            response_request_from_json(&responses)
+                .unwrap()
+                .response_format,
+            Some(expected)
+        );
+        assert_eq!(
+            num_tokens_from_request_json(&chat).unwrap().prompt_tokens,
+            num_tokens_from_request_json(&responses)
+                .unwrap()
+                .prompt_tokens
+        );
+        let without = json!({ "model": "gpt-4o", "input": "Hi" });
+        assert!(
+            num_tokens_from_request_json(&responses)
+                .unwrap()
+                .prompt_tokens
+                > num_tokens_from_request_json(&without)
+                    .unwrap()
+                    .prompt_tokens
+        );
+    }
+
+    #[test]
+    fn test_media_parts() {
+        let tokens = |body| num_tokens_from_request_json(&body).map(|t| t.prompt_tokens);
+        let text = tokens(json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "user", "content": [{ "type": "text", "text": "What is this?" }] }]
+        }))
+        .unwrap();
+        let chat = tokens(json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "user", "content": [
+                { "type": "text", "text": "What is this?" },
+                { "type": "image_url", "image_url": { "url": "https://example.com/a.png", "detail": "low" } }
+            ] }]
+        }))
+        .unwrap();
+        assert_eq!(chat, text + 85);
+
+        let responses = tokens(json!({
+            "model": "gpt-4o",
+            "input": [{ "role": "user", "content": [
+                { "type": "input_text", "text": "What is this?" },
+                { "type": "input_image", "image_url": "https://example.com/a.png" }
+            ] }]
+        }))
+        .unwrap();
+        let high = ImageInput::unknown_size(crate::multimodal::ImageDetail::High);
+        assert_eq!(
+            responses,
+            text + crate::multimodal::num_tokens_from_image("gpt-4o", &high).unwrap()
+        );
+
+        assert!(tokens(json!({
+            "model": "gpt-4o",
+            "input": [{ "role": "user", "content": [
+                { "type": "input_file", "file_id": "file-abc" }
+            ] }]
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn test_counting_with_counter() {
+        let body = json!({
+            "model": "gpt-4o",
+            "input": [{ "role": "user", "content": "Hello!" }]
+        });
+        let counter = ChatCounter::with_counter("gpt-4o", crate::o200k_base_singleton()).unwrap();
+        assert_eq!(
+            counter.num_tokens_from_request_json(&body).unwrap(),
+            num_tokens_from_request_json(&body).unwrap()
+        );
+        let other = ChatCounter::with_counter("gpt-4", crate::o200k_base_singleton()).unwrap();
+        assert!(other.num_tokens_from_request_json(&body).is_err());
+    }
+
+    #[test]
+    fn test_legacy_functions() {
+        let body = json!({
+            "model": "gpt-3.5-turbo",
+            "messages": [{ "role": "user", "content": "Hi" }],
+            "functions": [{ "name": "greet", "parameters": { "type": "object", "properties": {} } }],
+            "function_call": { "name": "greet" }
+        });
+        let request = chat_request_from_json(&body).unwrap();
+        assert_eq!(request.tools.len(), 1);
+        assert_eq!(
+            request.tool_choice,
+            Some(ToolChoice::Function("greet".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_responses_request() {
+        let body = json!({
+            "model": "gpt-4o",
+            "instructions": "Be brief.",
+            "input": [
+                { "role": "user", "content": "Weather in Paris?" },
+                { "type": "reasoning", "summary": [{ "type": "summary_text", "text": "Look it up." }] },
+                {
+                    "type": "function_call",
+                    "call_id": "call_1",
+                    "name": "get_weather",
+                    "arguments": "{\"city\":\"Paris\"}"
+                },
+                { "type": "function_call_output", "call_id": "call_1", "output": "18C" }
+            ],
+            "tools": [
+                { "type": "function", "name": "get_weather", "parameters": { "type": "object" } },
+                { "type": "web_search" }
+            ],
+            "tool_choice": "required",
+            "max_output_tokens": 500
+        });
+        let request = response_request_from_json(&body).unwrap();
+        assert_eq!(request.input.len(), 4);
+        assert_eq!(request.tools.len(), 1);
+        assert_eq!(request.tool_choice, Some(ToolChoice::Required));
+        let tokens = num_tokens_from_request_json(&body).unwrap();
+        assert_eq!(
+            tokens.prompt_tokens,
+            num_tokens_from_response_request("gpt-4o", &request).unwrap()
+        );
+        assert_eq!(tokens.max_completion_tokens, Some(500));
+
+        // Items that cannot be counted are rejected rather than skipped
+        for item in [
+            json!({ "type": "item_reference", "id": "msg_1" }),
+            json!({ "type": "web_search_call", "id": "ws_1", "status": "completed" }),
+            json!({ "type": "computer_call_output", "call_id": "call_2", "output": {} }),
+            json!({ "content": "No role" }),
+            json!({ "type": "function_call", "call_id": "call_3", "arguments": "{}" }),
+        ] {
+            let body = json!({ "model": "gpt-4o", "input": [item] });
+            assert!(num_tokens_from_request_json(&body).is_err());
+        }
+    }
+
+    #[test]
+    fn test_custom_tools() {
+        let chat = json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "user", "content": "Run it" }],
+            "tools": [{ "type": "custom", "custom": { "name": "shell", "description": "Run a command" } }],
+            "tool_choice": { "type": "custom", "custom": { "name": "shell" } }
+        });
+        let responses = json!({
+            "model": "gpt-4o",
+            "input": [{ "role": "user", "content": "Run it" }],
+            "tools": [{ "type": "custom", "name": "shell", "description": "Run a command" }],
+            "tool_choice": { "type": "custom", "name": "shell" }
+        });
+        let chat_request = chat_request_from_json(&chat).unwrap();
+        let response_request = response_request_from_json(&responses).unwrap();
+        assert_eq!(chat_request.tools, response_request.tools);
+        assert_eq!(chat_request.tool_choice, response_request.tool_choice);
+        assert_eq!(
+            chat_request.tool_choice,
+            Some(ToolChoice::Function("shell".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_string_input() {
+        let body = json!({ "model": "gpt-4o", "input": "Hello!" });
+        let messages = vec![ChatCompletionRequestMessage {
+            role: "user".to_string(),
+            content: Some("Hello!".into()),
+            ..Default::default()
+        }];
+        assert_eq!(
+            num_tokens_from_request_json(&body).unwrap().prompt_tokens,
+            num_tokens_from_messages("gpt-4o", &messages).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_requests() {
+        assert!(num_tokens_from_request_json(&json!({ "messages": [] })).is_err());
+        assert!(num_tokens_from_request_json(&json!({ "model": "gpt-4o" })).is_err());
+        assert!(num_tokens_from_request_json(&json!({ "model": "gpt-4o", "input": 1 })).is_err());
+        assert!(
+            num_tokens_from_request_json(&json!({ "model": "gpt-4o", "messages": [{}] })).is_err()
+        );
+        assert!(num_tokens_from_request_json_str("{").is_err());
+    }
+}
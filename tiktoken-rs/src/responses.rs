@@ -0,0 +1,254 @@
+/*!
+ * counts tokens for the input of Responses API requests.
+ *
+ * The Responses API presents the same conversation to the model as Chat Completions, with a
+ * different shape: `instructions` become a leading developer message, function calls and their
+ * outputs are separate items, and reasoning items from earlier turns can be sent back. A
+ * [`ResponseRequest`] converts into a [`ChatCompletionRequest`], which is then counted with
+ * [`num_tokens_from_request`](crate::num_tokens_from_request).
+ */
+
+use anyhow::Result;
+
+use crate::counter::TokenCounter;
+#[cfg(feature = "tools")]
+use crate::tools::{FunctionDefinition, ResponseFormat};
+use crate::{
+    ChatCompletionRequest, ChatCompletionRequestMessage, ChatCounter, MessageContent, ToolCall,
+    ToolChoice,
+};
+
+/// An item of the `input` of a Responses API request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseInputItem {
+    /// A message from the user, developer, system or assistant.
+    Message(ChatCompletionRequestMessage),
+    /// A call to a function (or custom tool) made by the model.
+    FunctionCall {
+        /// The ID that links the call to its output.
+        call_id: String,
+        /// The name of the function.
+        name: String,
+        /// The arguments of the call, usually as JSON.
+        arguments: String,
+    },
+    /// The output of a function (or custom tool) call.
+    FunctionCallOutput {
+        /// The ID of the call that produced this output.
+        call_id: String,
+        /// The output of the call.
+        output: String,
+    },
+    /// The reasoning of the model from an earlier turn.
+    ///
+    /// Only the summary text is counted. The encrypted reasoning content that the API may
+    /// restore is not visible to the client.
+    Reasoning {
+        /// The paragraphs of the reasoning summary.
+        summary: Vec<String>,
+    },
+}
+
+impl ResponseInputItem {
+    /// Creates a message item with the given role and content.
+    pub fn message(role: impl Into<String>, content: impl Into<MessageContent>) -> Self {
+        Self::Message(ChatCompletionRequestMessage {
+            role: role.into(),
+            content: Some(content.into()),
+            ..Default::default()
+        })
+    }
+}
+
+/// The parts of a Responses API request that make up the prompt.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ResponseRequest {
+    /// The `instructions`, inserted as a developer message before the input.
+    pub instructions: Option<String>,
+    /// The `input` items.
+    pub input: Vec<ResponseInputItem>,
+    /// The functions the model may call. Built-in tools such as web search are not counted.
+    /// Requires the `tools` feature.
+    #[cfg(feature = "tools")]
+    pub tools: Vec<FunctionDefinition>,
+    /// Which tool the model should call, if any. `None` means the field is omitted.
+    pub tool_choice: Option<ToolChoice>,
+    /// The format the model must output (`text.format`). `None` means the field is omitted.
+    /// Requires the `tools` feature.
+    #[cfg(feature = "tools")]
+    pub response_format: Option<ResponseFormat>,
+}
+
+impl From<&ResponseRequest> for ChatCompletionRequest {
+    /// Converts the request into the equivalent chat request. Consecutive function calls are
+    /// merged into a single assistant message, like parallel tool calls in chat.
+    fn from(request: &ResponseRequest) -> Self {
+        let mut messages = Vec::with_capacity(request.input.len() + 1);
+        if let Some(instructions) = &request.instructions {
+            messages.push(ChatCompletionRequestMessage {
+                role: "developer".to_string(),
+                content: Some(instructions.as_str().into()),
+                ..Default::default()
+            });
+        }
+        for item in &request.input {
+            match item {
+                ResponseInputItem::Message(message) => messages.push(message.clone()),
+                ResponseInputItem::FunctionCall {
+                    call_id,
+                    name,
+                    arguments,
+                } => {
+                    let call = ToolCall::function(call_id, name, arguments);
+                    match messages.last_mut() {
+                        Some(last)
+                            if last.role == "assistant"
+                                && last.content.is_none()
+                                && !last.tool_calls.is_empty() =>
+                        {
+                            last.tool_calls.push(call)
+                        }
+                        _ => messages.push(ChatCompletionRequestMessage {
+                            role: "assistant".to_string(),
+                            tool_calls: vec![call],
+                            ..Default::default()
+                        }),
+                    }
+                }
+                ResponseInputItem::FunctionCallOutput { call_id, output } => {
+                    messages.push(ChatCompletionRequestMessage {
+                        role: "tool".to_string(),
+                        content: Some(output.as_str().into()),
+                        tool_call_id: Some(call_id.clone()),
+                        ..Default::default()
+                    })
+                }
+                ResponseInputItem::Reasoning { summary } => {
+                    messages.push(ChatCompletionRequestMessage {
+                        role: "assistant".to_string(),
+                        content: Some(summary.join("\n\n").into()),
+                        ..Default::default()
+                    })
+                }
+            }
+        }
+        Self {
+            messages,
+            #[cfg(feature = "tools")]
+            tools: request.tools.clone(),
+            tool_choice: request.tool_choice.clone(),
+            #[cfg(feature = "tools")]
+            response_format: request.response_format.clone(),
+        }
+    }
+}
+
+/// Returns the number of input tokens of a Responses API request.
+///
+/// # Arguments
+///
+/// * `model` - A string slice representing the model name, e.g., `"gpt-4o"`.
+/// * `request` - The instructions, input items and tools of the request.
+///
+/// # Errors
+///
+/// Returns an error if the request cannot be counted for the model
+/// (see [`num_tokens_from_messages`](crate::num_tokens_from_messages)).
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::responses::{num_tokens_from_response_request, ResponseInputItem, ResponseRequest};
+///
+/// let request = ResponseRequest {
+///     instructions: Some("Answer in one word.".to_string()),
+///     input: vec![ResponseInputItem::message("user", "What color is the sky?")],
+///     ..Default::default()
+/// };
+/// let num_tokens = num_tokens_from_response_request("gpt-4o", &request).unwrap();
+/// assert!(num_tokens > 0);
+/// ```
+pub fn num_tokens_from_response_request(model: &str, request: &ResponseRequest) -> Result<usize> {
+    ChatCounter::new(model)?.num_tokens_from_response_request(request)
+}
+
+impl<C: TokenCounter + ?Sized> ChatCounter<'_, C> {
+    /// Returns the number of input tokens of a Responses API request, see
+    /// [`num_tokens_from_response_request`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a content part cannot be counted for the model.
+    pub fn num_tokens_from_response_request(&self, request: &ResponseRequest) -> Result<usize> {
+        self.num_tokens_from_request(&request.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{num_tokens_from_messages, num_tokens_from_request};
+
+    #[test]
+    fn test_instructions_are_a_developer_message() {
+        let request = ResponseRequest {
+            instructions: Some("Be brief.".to_string()),
+            input: vec![ResponseInputItem::message("user", "Hi")],
+            ..Default::default()
+        };
+        let messages = vec![
+            ChatCompletionRequestMessage {
+                role: "developer".to_string(),
+                content: Some("Be brief.".into()),
+                ..Default::default()
+            },
+            ChatCompletionRequestMessage {
+                role: "user".to_string(),
+                content: Some("Hi".into()),
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            num_tokens_from_response_request("gpt-4o", &request).unwrap(),
+            num_tokens_from_messages("gpt-4o", &messages).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_function_calls_and_reasoning() {
+        let call = |call_id: &str, city: &str| ResponseInputItem::FunctionCall {
+            call_id: call_id.to_string(),
+            name: "get_weather".to_string(),
+            arguments: format!(r#"{{"city":"{}"}}"#, city),
+        };
+        let request = ResponseRequest {
+            input: vec![
+                ResponseInputItem::message("user", "Weather in Paris and Rome?"),
+                ResponseInputItem::Reasoning {
+                    summary: vec!["Look up both cities.".to_string()],
+                },
+                call("call_1", "Paris"),
+                call("call_2", "Rome"),
+                ResponseInputItem::FunctionCallOutput {
+                    call_id: "call_1".to_string(),
+                    output: "18C".to_string(),
+                },
+                ResponseInputItem::FunctionCallOutput {
+                    call_id: "call_2".to_string(),
+                    output: "24C".to_string(),
+                },
+            ],
+            ..Default::default()
+        };
+        let chat = ChatCompletionRequest::from(&request);
+        let roles: Vec<&str> = chat.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["user", "assistant", "assistant", "tool", "tool"]);
+        assert_eq!(chat.messages[2].tool_calls.len(), 2);
+        assert_eq!(chat.messages[2].tool_calls[1].id(), "call_2");
+        assert_eq!(chat.messages[4].tool_call_id.as_deref(), Some("call_2"));
+        assert_eq!(
+            num_tokens_from_response_request("gpt-4o", &request).unwrap(),
+            num_tokens_from_request("gpt-4o", &chat).unwrap()
+        );
+    }
+}
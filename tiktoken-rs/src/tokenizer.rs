@@ -42,8 +42,9 @@ const MODEL_PREFIX_TO_TOKENIZER: &[(&str, Tokenizer)] = &[
     ("gpt-4.5-", Tokenizer::O200kBase),
     ("gpt-4.1-", Tokenizer::O200kBase),
     ("chatgpt-4o-", Tokenizer::O200kBase),
-    ("gpt-4o-", Tokenizer::O200kBase), // e.g., gpt-4o-2024-05-13
-    ("gpt-4-", Tokenizer::Cl100kBase), // e.g., gpt-4-0314, etc., plus gpt-4-32k
+    ("gpt-4o-", Tokenizer::O200kBase),   // e.g., gpt-4o-2024-05-13
+    ("gpt-audio", Tokenizer::O200kBase), // e.g., gpt-audio, gpt-audio-mini
+    ("gpt-4-", Tokenizer::Cl100kBase),   // e.g., gpt-4-0314, etc., plus gpt-4-32k
     ("gpt-3.5-turbo-", Tokenizer::Cl100kBase), // e.g, gpt-3.5-turbo-0301, -0401, etc.
     ("gpt-35-turbo-", Tokenizer::Cl100kBase), // Azure deployment name
     ("gpt-oss-", Tokenizer::O200kHarmony),
@@ -228,6 +229,9 @@ mod tests {
             get_tokenizer("gpt-3.5-turbo-0301"),
             Some(Tokenizer::Cl100kBase)
         );
+        // Audio
+        assert_eq!(get_tokenizer("gpt-audio"), Some(Tokenizer::O200kBase));
+        assert_eq!(get_tokenizer("gpt-audio-mini"), Some(Tokenizer::O200kBase));
         // gpt-oss
         assert_eq!(get_tokenizer("gpt-oss-20b"), Some(Tokenizer::O200kHarmony));
         assert_eq!(get_tokenizer("gpt-oss-120b"), Some(Tokenizer::O200kHarmony));
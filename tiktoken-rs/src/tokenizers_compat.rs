@@ -0,0 +1,314 @@
+/*!
+ * An adapter with the API shape of the HuggingFace
+ * [`tokenizers`](https://crates.io/crates/tokenizers) crate, behind the `tokenizers-compat`
+ * feature.
+ *
+ * [`TiktokenTokenizer`] wraps a [`CoreBPE`] and exposes `encode`, `decode`, `get_vocab_size`,
+ * `token_to_id` and `id_to_token` with the same names and arguments as
+ * `tokenizers::Tokenizer`, so a tiktoken encoding can be dropped into code written against
+ * that API. All encoding and decoding is done by the wrapped [`CoreBPE`].
+ *
+ * # Example
+ *
+ * ```
+ * use tiktoken_rs::tokenizers_compat::TiktokenTokenizer;
+ *
+ * let tokenizer = TiktokenTokenizer::for_model("gpt-4o").unwrap();
+ * let encoding = tokenizer.encode("Hello world<|endoftext|>", true).unwrap();
+ * assert_eq!(encoding.get_tokens(), &["Hello", " world", "<|endoftext|>"]);
+ * assert_eq!(encoding.get_offsets(), &[(0, 5), (5, 11), (11, 24)]);
+ * assert_eq!(encoding.get_special_tokens_mask(), &[0, 0, 1]);
+ *
+ * let text = tokenizer.decode(encoding.get_ids(), true).unwrap();
+ * assert_eq!(text, "Hello world");
+ * ```
+ */
+
+use anyhow::Result;
+
+use crate::{bpe_for_model, CoreBPE, Rank};
+
+/// The output of [`TiktokenTokenizer::encode`], shaped like `tokenizers::Encoding`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Encoding {
+    ids: Vec<u32>,
+    tokens: Vec<String>,
+    offsets: Vec<(usize, usize)>,
+    special_tokens_mask: Vec<u32>,
+}
+
+impl Encoding {
+    /// The token ids.
+    pub fn get_ids(&self) -> &[u32] {
+        &self.ids
+    }
+
+    /// The text of each token. Tokens that are not valid UTF-8 on their own, e.g. part of a
+    /// multi-byte character, are decoded lossily.
+    pub fn get_tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    /// The `(start, end)` range of each token in the input text, in bytes, or in characters
+    /// for [`TiktokenTokenizer::encode_char_offsets`].
+    pub fn get_offsets(&self) -> &[(usize, usize)] {
+        &self.offsets
+    }
+
+    /// `1` for special tokens and `0` for the others.
+    pub fn get_special_tokens_mask(&self) -> &[u32] {
+        &self.special_tokens_mask
+    }
+
+    /// The attention mask, which is `1` for every token since encodings are never padded.
+    pub fn get_attention_mask(&self) -> Vec<u32> {
+        vec![1; self.ids.len()]
+    }
+
+    /// The type ids, which are `0` for every token since there is a single sequence.
+    pub fn get_type_ids(&self) -> Vec<u32> {
+        vec![0; self.ids.len()]
+    }
+
+    /// The number of tokens.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Whether there are no tokens.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+/// A [`CoreBPE`] with the API shape of `tokenizers::Tokenizer`.
+///
+/// tiktoken encodings have no post-processor, so the `add_special_tokens` argument of
+/// [`TiktokenTokenizer::encode`] has no effect: no tokens are ever added around the input.
+/// Special tokens in the input text, e.g. `<|endoftext|>`, are always encoded as special
+/// tokens, like the added tokens of `tokenizers`. Use [`TiktokenTokenizer::encode_ordinary`]
+/// to encode them as plain text.
+#[derive(Clone, Copy)]
+pub struct TiktokenTokenizer<'a> {
+    bpe: &'a CoreBPE,
+}
+
+impl<'a> TiktokenTokenizer<'a> {
+    /// Wraps a [`CoreBPE`].
+    pub fn new(bpe: &'a CoreBPE) -> Self {
+        Self { bpe }
+    }
+
+    /// The wrapped [`CoreBPE`].
+    pub fn bpe(&self) -> &'a CoreBPE {
+        self.bpe
+    }
+
+    /// Encodes `text`, with offsets in bytes. Special tokens in the text are encoded as
+    /// special tokens.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to encode.
+    /// * `add_special_tokens` - Whether to add the special tokens of the post-processor. tiktoken
+    ///   encodings have none, so this has no effect.
+    pub fn encode(&self, text: &str, add_special_tokens: bool) -> Result<Encoding> {
+        let _ = add_special_tokens;
+        self.encoding(self.bpe.encode_with_special_tokens(text))
+    }
+
+    /// Encodes `text` like [`TiktokenTokenizer::encode`], with special tokens in the text
+    /// encoded as plain text.
+    pub fn encode_ordinary(&self, text: &str) -> Result<Encoding> {
+        self.encoding(self.bpe.encode_ordinary(text))
+    }
+
+    /// Encodes `text` like [`TiktokenTokenizer::encode`], with offsets in characters.
+    ///
+    /// A token that covers part of a multi-byte character is given the whole character.
+    pub fn encode_char_offsets(&self, text: &str, add_special_tokens: bool) -> Result<Encoding> {
+        let mut encoding = self.encode(text, add_special_tokens)?;
+        // Number of characters starting before each byte offset, plus one past the end.
+        let mut chars_before = vec![0; text.len() + 1];
+        let mut count = 0;
+        for (offset, byte) in chars_before.iter_mut().zip(text.bytes().chain([0])) {
+            *offset = count;
+            // Count character boundaries, i.e. bytes that are not UTF-8 continuation bytes.
+            if byte & 0b1100_0000 != 0b1000_0000 {
+                count += 1;
+            }
+        }
+        for (start, end) in encoding.offsets.iter_mut() {
+            let start_in_char = !text.is_char_boundary(*start);
+            *start = chars_before[*start] - start_in_char as usize;
+            *end = chars_before[*end];
+        }
+        Ok(encoding)
+    }
+
+    /// Encodes each text with [`TiktokenTokenizer::encode`].
+    pub fn encode_batch(&self, texts: &[&str], add_special_tokens: bool) -> Result<Vec<Encoding>> {
+        texts
+            .iter()
+            .map(|text| self.encode(text, add_special_tokens))
+            .collect()
+    }
+
+    /// Decodes token ids into text.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an id is not in the vocabulary, or if the tokens do not decode to
+    /// valid UTF-8.
+    pub fn decode(&self, ids: &[u32], skip_special_tokens: bool) -> Result<String> {
+        if !skip_special_tokens {
+            return self.bpe.decode(ids);
+        }
+        let ids: Vec<Rank> = ids
+            .iter()
+            .copied()
+            .filter(|id| !self.bpe.special_tokens_decoder.contains_key(id))
+            .collect();
+        self.bpe.decode(&ids)
+    }
+
+    /// Decodes each sequence of ids with [`TiktokenTokenizer::decode`].
+    pub fn decode_batch(
+        &self,
+        sequences: &[&[u32]],
+        skip_special_tokens: bool,
+    ) -> Result<Vec<String>> {
+        sequences
+            .iter()
+            .map(|ids| self.decode(ids, skip_special_tokens))
+            .collect()
+    }
+
+    /// The number of tokens in the vocabulary, with or without the special tokens.
+    pub fn get_vocab_size(&self, with_added_tokens: bool) -> usize {
+        let special = if with_added_tokens {
+            self.bpe.special_tokens_encoder.len()
+        } else {
+            0
+        };
+        self.bpe.encoder.len() + special
+    }
+
+    /// The id of a token, given its text.
+    pub fn token_to_id(&self, token: &str) -> Option<u32> {
+        self.bpe
+            .special_tokens_encoder
+            .get(token)
+            .or_else(|| self.bpe.encoder.get(token.as_bytes()))
+            .copied()
+    }
+
+    /// The text of a token, decoded lossily if it is not valid UTF-8 on its own.
+    pub fn id_to_token(&self, id: u32) -> Option<String> {
+        self.token_bytes(id)
+            .ok()
+            .map(|(bytes, _)| String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    fn encoding(&self, ids: Vec<Rank>) -> Result<Encoding> {
+        let mut encoding = Encoding {
+            tokens: Vec::with_capacity(ids.len()),
+            offsets: Vec::with_capacity(ids.len()),
+            special_tokens_mask: Vec::with_capacity(ids.len()),
+            ..Default::default()
+        };
+        let mut start = 0;
+        for &id in &ids {
+            let (bytes, special) = self.token_bytes(id)?;
+            encoding
+                .tokens
+                .push(String::from_utf8_lossy(bytes).into_owned());
+            encoding.offsets.push((start, start + bytes.len()));
+            encoding.special_tokens_mask.push(special as u32);
+            start += bytes.len();
+        }
+        encoding.ids = ids;
+        Ok(encoding)
+    }
+
+    fn token_bytes(&self, id: Rank) -> Result<(&'a [u8], bool)> {
+        if let Some(bytes) = self.bpe.decoder.get(&id) {
+            return Ok((bytes, false));
+        }
+        let bytes = self
+            .bpe
+            .special_tokens_decoder
+            .get(&id)
+            .ok_or(crate::DecodeKeyError { token: id })?;
+        Ok((bytes, true))
+    }
+}
+
+impl TiktokenTokenizer<'static> {
+    /// Wraps the cached [`CoreBPE`] for a model, see [`bpe_for_model`].
+    pub fn for_model(model: &str) -> Result<Self> {
+        Ok(Self::new(bpe_for_model(model)?))
+    }
+}
+
+impl<'a> From<&'a CoreBPE> for TiktokenTokenizer<'a> {
+    fn from(bpe: &'a CoreBPE) -> Self {
+        Self::new(bpe)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::o200k_base_singleton;
+
+    #[test]
+    fn test_encode_offsets() {
+        let tokenizer = TiktokenTokenizer::new(o200k_base_singleton());
+        let text = "naïve café 👋<|endoftext|>";
+        let encoding = tokenizer.encode(text, true).unwrap();
+        assert_eq!(
+            encoding.get_ids(),
+            o200k_base_singleton().encode_with_special_tokens(text)
+        );
+        assert_eq!(encoding.get_special_tokens_mask().last(), Some(&1));
+        for (&(start, end), id) in encoding.get_offsets().iter().zip(encoding.get_ids()) {
+            assert_eq!(
+                &text.as_bytes()[start..end],
+                o200k_base_singleton().decode_bytes(&[*id]).unwrap()
+            );
+        }
+
+        let chars = tokenizer.encode_char_offsets(text, true).unwrap();
+        let num_chars = text.chars().count();
+        assert_eq!(chars.get_offsets().first().map(|o| o.0), Some(0));
+        assert_eq!(chars.get_offsets().last().map(|o| o.1), Some(num_chars));
+        assert!(chars.get_offsets().iter().all(|&(start, end)| start <= end));
+
+        // `add_special_tokens` only adds tokens around the input, which tiktoken never does
+        assert_eq!(tokenizer.encode(text, false).unwrap(), encoding);
+
+        let ordinary = tokenizer.encode_ordinary("<|endoftext|>").unwrap();
+        assert!(ordinary.len() > 1);
+        assert!(ordinary.get_special_tokens_mask().iter().all(|&m| m == 0));
+    }
+
+    #[test]
+    fn test_decode_and_vocab() {
+        let tokenizer = TiktokenTokenizer::for_model("gpt-4o").unwrap();
+        let encoding = tokenizer.encode("Hello<|endoftext|>", true).unwrap();
+        assert_eq!(
+            tokenizer.decode(encoding.get_ids(), false).unwrap(),
+            "Hello<|endoftext|>"
+        );
+        assert_eq!(tokenizer.decode(encoding.get_ids(), true).unwrap(), "Hello");
+        assert!(tokenizer.decode(&[u32::MAX], false).is_err());
+
+        assert!(tokenizer.get_vocab_size(true) > tokenizer.get_vocab_size(false));
+        let id = tokenizer.token_to_id("Hello").unwrap();
+        assert_eq!(tokenizer.id_to_token(id).as_deref(), Some("Hello"));
+        let eot = tokenizer.token_to_id("<|endoftext|>").unwrap();
+        assert_eq!(encoding.get_ids().last(), Some(&eot));
+        assert_eq!(tokenizer.id_to_token(u32::MAX), None);
+    }
+}
@@ -0,0 +1,577 @@
+/*!
+ * counts tokens for the tool (function) definitions and response formats sent with chat requests.
+ *
+ * The API does not send tool definitions to the model as JSON. They are rendered into the
+ * system prompt as a TypeScript-like namespace, and that rendering is what consumes tokens:
+ *
+ * ```text
+ * namespace functions {
+ *
+ * // Get the current weather
+ * type get_current_weather = (_: {
+ * // The city and state, e.g. San Francisco, CA
+ * location: string,
+ * unit?: "celsius" | "fahrenheit",
+ * }) => any;
+ *
+ * } // namespace functions
+ * ```
+ *
+ * The format is not documented by OpenAI; it follows the reverse-engineered rendering from
+ * <https://github.com/hmarr/openai-chat-tokens>, which matches API usage for typical schemas.
+ *
+ * JSON schema response formats (structured outputs) are also rendered into the prompt, but
+ * the API does not document how, and its rendering has not been reverse-engineered. Their
+ * count is an approximation: the response format section of the documented Harmony format
+ * used by gpt-oss (see [`render_response_format`]), which has not been checked against the
+ * `prompt_tokens` the API reports.
+ *
+ * This module requires the `tools` feature, which is also enabled by the `serde` and
+ * `async-openai` features.
+ *
+ * The model sees schema properties in the order they were declared, but a `serde_json` object
+ * only keeps that order if the `preserve_order` feature of `serde_json` is enabled, and this
+ * crate does not enable it for you. Required properties are therefore rendered first, in the
+ * order of the `required` list, followed by the optional ones in the order of the object. For
+ * the usual schemas that list required properties in declaration order before the optional
+ * ones, this is the declared order either way.
+ */
+
+use anyhow::Result;
+use serde_json::{Map, Value};
+
+use crate::bpe_for_model;
+use crate::counter::TokenCounter;
+
+/// A function the model may call, as given in the `tools` (or legacy `functions`) list of a request.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FunctionDefinition {
+    /// The name of the function.
+    pub name: String,
+    /// A description of what the function does.
+    pub description: Option<String>,
+    /// The parameters the function accepts, described as a JSON Schema object.
+    pub parameters: Option<Value>,
+}
+
+/// Extra tokens added once when tool definitions are present in a request.
+const FUNCTION_DEFINITIONS_OVERHEAD: usize = 9;
+
+/// A JSON schema the model's output must follow, as given in a `json_schema` response format.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct JsonSchemaFormat {
+    /// The name of the response format.
+    pub name: String,
+    /// A description of what the response format is for.
+    pub description: Option<String>,
+    /// The JSON Schema object the output must follow.
+    pub schema: Option<Value>,
+    /// Whether strict schema adherence is enabled. It constrains decoding, not the prompt,
+    /// so it does not change the count.
+    pub strict: Option<bool>,
+}
+
+/// The format the model must output, as given in the `response_format` of a chat request
+/// (or the `text.format` of a Responses request).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum ResponseFormat {
+    /// Plain text, the default.
+    #[default]
+    Text,
+    /// JSON mode. Nothing is added to the prompt; the messages must ask for JSON themselves.
+    JsonObject,
+    /// Structured outputs. The schema is rendered into the prompt.
+    JsonSchema(JsonSchemaFormat),
+}
+
+/// Renders function definitions the way they are presented to the model.
+///
+/// # Examples
+///
+/// ```
+/// use serde_json::json;
+/// use tiktoken_rs::tools::{render_function_definitions, FunctionDefinition};
+///
+/// let functions = vec![FunctionDefinition {
+///     name: "get_weather".to_string(),
+///     description: Some("Get the weather".to_string()),
+///     parameters: Some(json!({
+///         "type": "object",
+///         "properties": { "city": { "type": "string" } },
+///         "required": ["city"],
+///     })),
+/// }];
+/// assert_eq!(
+///     render_function_definitions(&functions),
+///     "namespace functions {\n\n// Get the weather\ntype get_weather = (_: {\ncity: string,\n}) => any;\n\n} // namespace functions"
+/// );
+/// ```
+pub fn render_function_definitions(functions: &[FunctionDefinition]) -> String {
+    let mut lines = vec!["namespace functions {".to_string(), String::new()];
+    for function in functions {
+        if let Some(description) = &function.description {
+            lines.push(format!("// {}", description));
+        }
+        match &function.parameters {
+            Some(parameters) if has_properties(parameters) => {
+                lines.push(format!("type {} = (_: {{", function.name));
+                lines.push(render_object_properties(parameters, 0));
+                lines.push("}) => any;".to_string());
+            }
+            _ => lines.push(format!("type {} = () => any;", function.name)),
+        }
+        lines.push(String::new());
+    }
+    lines.push("} // namespace functions".to_string());
+    lines.join("\n")
+}
+
+/// Returns the number of prompt tokens consumed by the given function definitions.
+///
+/// This includes the fixed overhead added whenever a request has tools. Requests that also
+/// contain a system message get a small discount, which [`num_tokens_from_request`] accounts for.
+///
+/// [`num_tokens_from_request`]: crate::num_tokens_from_request
+///
+/// # Arguments
+///
+/// * `model` - A string slice representing the model name, e.g., `"gpt-4o"`.
+/// * `functions` - The function definitions sent with the request.
+///
+/// # Errors
+///
+/// Returns an error if no tokenizer is found for the given model.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::tools::{num_tokens_from_functions, FunctionDefinition};
+///
+/// let functions = vec![FunctionDefinition {
+///     name: "get_time".to_string(),
+///     ..Default::default()
+/// }];
+/// let num_tokens = num_tokens_from_functions("gpt-4o", &functions).unwrap();
+/// assert!(num_tokens > 0);
+/// ```
+pub fn num_tokens_from_functions(model: &str, functions: &[FunctionDefinition]) -> Result<usize> {
+    let bpe = bpe_for_model(model)?;
+    Ok(count_function_definitions(bpe, functions))
+}
+
+pub(crate) fn count_function_definitions<C>(bpe: &C, functions: &[FunctionDefinition]) -> usize
+where
+    C: TokenCounter + ?Sized,
+{
+    if functions.is_empty() {
+        return 0;
+    }
+    bpe.count_with_special_tokens(&render_function_definitions(functions))
+        + FUNCTION_DEFINITIONS_OVERHEAD
+}
+
+/// Renders a response format as an approximation of how it is presented to the model, or
+/// returns `None` if it adds nothing to the prompt.
+///
+/// A JSON schema is rendered like the response format section of the Harmony format used by
+/// gpt-oss, with the schema as compact JSON. The API does not document the rendering it uses
+/// for other models, so this is not their exact prompt:
+///
+/// ```text
+/// # Response Formats
+///
+/// ## {name}
+///
+/// // {description}
+/// {schema}
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use serde_json::json;
+/// use tiktoken_rs::tools::{render_response_format, JsonSchemaFormat, ResponseFormat};
+///
+/// let format = ResponseFormat::JsonSchema(JsonSchemaFormat {
+///     name: "answer".to_string(),
+///     schema: Some(json!({ "type": "object" })),
+///     strict: Some(true),
+///     ..Default::default()
+/// });
+/// assert_eq!(
+///     render_response_format(&format).unwrap(),
+///     "# Response Formats\n\n## answer\n\n{\"type\":\"object\"}"
+/// );
+/// assert_eq!(render_response_format(&ResponseFormat::JsonObject), None);
+/// ```
+pub fn render_response_format(format: &ResponseFormat) -> Option<String> {
+    let ResponseFormat::JsonSchema(format) = format else {
+        return None;
+    };
+    let mut lines = vec![
+        "# Response Formats".to_string(),
+        String::new(),
+        format!("## {}", format.name),
+        String::new(),
+    ];
+    if let Some(description) = &format.description {
+        lines.push(format!("// {}", description));
+    }
+    if let Some(schema) = &format.schema {
+        lines.push(schema.to_string());
+    }
+    Some(lines.join("\n"))
+}
+
+/// Returns an approximation of the number of prompt tokens consumed by the given response
+/// format: the number of tokens of its [rendering](render_response_format).
+///
+/// # Arguments
+///
+/// * `model` - A string slice representing the model name, e.g., `"gpt-4o"`.
+/// * `format` - The response format of the request.
+///
+/// # Errors
+///
+/// Returns an error if no tokenizer is found for the given model.
+///
+/// # Examples
+///
+/// ```
+/// use tiktoken_rs::tools::{num_tokens_from_response_format, ResponseFormat};
+///
+/// let num_tokens = num_tokens_from_response_format("gpt-4o", &ResponseFormat::JsonObject).unwrap();
+/// assert_eq!(num_tokens, 0);
+/// ```
+pub fn num_tokens_from_response_format(model: &str, format: &ResponseFormat) -> Result<usize> {
+    let bpe = bpe_for_model(model)?;
+    Ok(count_response_format(bpe, format))
+}
+
+pub(crate) fn count_response_format<C>(bpe: &C, format: &ResponseFormat) -> usize
+where
+    C: TokenCounter + ?Sized,
+{
+    render_response_format(format).map_or(0, |rendered| bpe.count_with_special_tokens(&rendered))
+}
+
+fn has_properties(schema: &Value) -> bool {
+    schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .is_some_and(|properties| !properties.is_empty())
+}
+
+fn render_object_properties(schema: &Value, indent: usize) -> String {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return String::new();
+    };
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let mut lines = Vec::new();
+    for (name, property) in ordered_properties(properties, &required) {
+        if indent < 2 {
+            if let Some(description) = property.get("description").and_then(Value::as_str) {
+                lines.push(format!("// {}", description));
+            }
+        }
+        let optional = if required.contains(&name) { "" } else { "?" };
+        lines.push(format!(
+            "{}{}: {},",
+            name,
+            optional,
+            render_type(property, indent)
+        ));
+    }
+    let padding = " ".repeat(indent);
+    lines
+        .iter()
+        .map(|line| format!("{}{}", padding, line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The required properties in the order of `required`, then the optional ones in the order of
+/// the map, see the [module documentation](self).
+fn ordered_properties<'a>(
+    properties: &'a Map<String, Value>,
+    required: &[&str],
+) -> Vec<(&'a str, &'a Value)> {
+    let mut ordered = Vec::with_capacity(properties.len());
+    for name in required {
+        if let Some((name, property)) = properties.get_key_value(*name) {
+            if !ordered.iter().any(|(seen, _)| *seen == name.as_str()) {
+                ordered.push((name.as_str(), property));
+            }
+        }
+    }
+    for (name, property) in properties {
+        if !required.contains(&name.as_str()) {
+            ordered.push((name.as_str(), property));
+        }
+    }
+    ordered
+}
+
+fn render_type(schema: &Value, indent: usize) -> String {
+    // Unions: `"type": ["string", "null"]`, `anyOf` and `oneOf`
+    if let Some(types) = schema.get("type").and_then(Value::as_array) {
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            return render_literals(values);
+        }
+        let mut variants = Vec::new();
+        for ty in types {
+            let mut variant = schema.clone();
+            variant["type"] = ty.clone();
+            variants.push(render_type(&variant, indent));
+        }
+        return render_union(variants);
+    }
+    if let Some(schemas) = ["anyOf", "oneOf"]
+        .iter()
+        .find_map(|key| schema.get(key).and_then(Value::as_array))
+    {
+        return render_union(schemas.iter().map(|s| render_type(s, indent)).collect());
+    }
+
+    let enum_values = schema.get("enum").and_then(Value::as_array);
+    match schema.get("type").and_then(Value::as_str) {
+        Some("string") => match enum_values {
+            Some(values) => render_literals(values),
+            None => "string".to_string(),
+        },
+        Some("number" | "integer") => match enum_values {
+            Some(values) => render_literals(values),
+            None => "number".to_string(),
+        },
+        Some("boolean") => "boolean".to_string(),
+        Some("null") => "null".to_string(),
+        Some("object") => format!("{{\n{}\n}}", render_object_properties(schema, indent + 2)),
+        Some("array") => match schema.get("items") {
+            Some(items) => {
+                let items = render_type(items, indent);
+                if items.contains(" | ") {
+                    format!("({})[]", items)
+                } else {
+                    format!("{}[]", items)
+                }
+            }
+            None => "any[]".to_string(),
+        },
+        // `enum` without `type`, or `$ref` and other schemas without a type
+        _ => match enum_values {
+            Some(values) => render_literals(values),
+            None => "any".to_string(),
+        },
+    }
+}
+
+fn render_literals(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(|v| match v {
+            Value::String(s) => format!("\"{}\"", s),
+            other => other.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+fn render_union(variants: Vec<String>) -> String {
+    let mut unique: Vec<String> = Vec::new();
+    for variant in variants {
+        if !unique.contains(&variant) {
+            unique.push(variant);
+        }
+    }
+    if unique.is_empty() {
+        return "any".to_string();
+    }
+    unique.join(" | ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_response_format_tokens() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "answer": { "type": "string" },
+                "confidence": { "type": "number" }
+            },
+            "required": ["answer", "confidence"],
+            "additionalProperties": false
+        });
+        let format = |strict| {
+            ResponseFormat::JsonSchema(JsonSchemaFormat {
+                name: "answer".to_string(),
+                description: Some("An answer with a confidence".to_string()),
+                schema: Some(schema.clone()),
+                strict,
+            })
+        };
+        let rendered = render_response_format(&format(Some(true))).unwrap();
+        assert_eq!(
+            rendered,
+            "# Response Formats\n\n## answer\n\n// An answer with a confidence\n\
+             {\"additionalProperties\":false,\"properties\":{\"answer\":{\"type\":\"string\"},\
+             \"confidence\":{\"type\":\"number\"}},\"required\":[\"answer\",\"confidence\"],\
+             \"type\":\"object\"}"
+        );
+        // Strictness constrains decoding, not the prompt
+        assert_eq!(
+            num_tokens_from_response_format("gpt-4o", &format(Some(true))).unwrap(),
+            num_tokens_from_response_format("gpt-4o", &format(None)).unwrap()
+        );
+        assert_eq!(
+            num_tokens_from_response_format("gpt-4o", &ResponseFormat::Text).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_render_function_without_parameters() {
+        let functions = vec![FunctionDefinition {
+            name: "foo".to_string(),
+            parameters: Some(json!({ "type": "object", "properties": {} })),
+            ..Default::default()
+        }];
+        assert_eq!(
+            render_function_definitions(&functions),
+            "namespace functions {\n\ntype foo = () => any;\n\n} // namespace functions"
+        );
+    }
+
+    #[test]
+    fn test_render_nested_types() {
+        let functions = vec![FunctionDefinition {
+            name: "create_event".to_string(),
+            description: Some("Create a calendar event".to_string()),
+            parameters: Some(json!({
+                "type": "object",
+                "properties": {
+                    "title": { "type": "string", "description": "Event title" },
+                    "priority": { "type": "integer", "enum": [1, 2, 3] },
+                    "visibility": { "type": "string", "enum": ["public", "private"] },
+                    "attendees": { "type": "array", "items": { "type": "string" } },
+                    "location": {
+                        "type": "object",
+                        "description": "Where the event happens",
+                        "properties": {
+                            "city": { "type": "string", "description": "City name" },
+                            "online": { "type": "boolean" },
+                        },
+                        "required": ["city"],
+                    },
+                },
+                "required": ["title"],
+            })),
+        }];
+        let expected = "namespace functions {
+
+// Create a calendar event
+type create_event = (_: {
+// Event title
+title: string,
+attendees?: string[],
+// Where the event happens
+location?: {
+  city: string,
+  online?: boolean,
+},
+priority?: 1 | 2 | 3,
+visibility?: \"public\" | \"private\",
+}) => any;
+
+} // namespace functions";
+        // Without `preserve_order`, the optional properties are sorted by name
+        assert_eq!(render_function_definitions(&functions), expected);
+    }
+
+    #[test]
+    fn test_render_required_properties_in_required_order() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "unit": { "type": "string" },
+                "location": { "type": "string" },
+                "days": { "type": "integer" },
+            },
+            "required": ["location", "days", "location", "missing"],
+        });
+        assert_eq!(
+            render_object_properties(&schema, 0),
+            "location: string,\ndays: number,\nunit?: string,"
+        );
+    }
+
+    #[test]
+    fn test_render_union_and_untyped_schemas() {
+        let render = |property: Value| {
+            render_object_properties(
+                &json!({
+                    "type": "object",
+                    "properties": { "x": property },
+                    "required": ["x"],
+                }),
+                0,
+            )
+        };
+        // Nullable type arrays, as used in strict schemas
+        assert_eq!(
+            render(json!({ "type": ["string", "null"] })),
+            "x: string | null,"
+        );
+        assert_eq!(
+            render(json!({ "type": ["string", "null"], "enum": ["a", "b", null] })),
+            "x: \"a\" | \"b\" | null,"
+        );
+        // anyOf and oneOf
+        assert_eq!(
+            render(json!({ "anyOf": [{ "type": "number" }, { "type": "boolean" }] })),
+            "x: number | boolean,"
+        );
+        assert_eq!(
+            render(json!({ "oneOf": [{ "type": "string" }, { "type": "null" }] })),
+            "x: string | null,"
+        );
+        assert_eq!(
+            render(json!({
+                "type": "array",
+                "items": { "anyOf": [{ "type": "string" }, { "type": "integer" }] }
+            })),
+            "x: (string | number)[],"
+        );
+        // enum without type
+        assert_eq!(
+            render(json!({ "enum": ["on", "off", 0] })),
+            "x: \"on\" | \"off\" | 0,"
+        );
+        // $ref and other schemas without a type
+        assert_eq!(render(json!({ "$ref": "#/$defs/address" })), "x: any,");
+        assert_eq!(render(json!({})), "x: any,");
+    }
+
+    #[test]
+    fn test_num_tokens_from_functions() {
+        assert_eq!(num_tokens_from_functions("gpt-4", &[]).unwrap(), 0);
+        let functions = vec![FunctionDefinition {
+            name: "foo".to_string(),
+            parameters: Some(json!({ "type": "object", "properties": {} })),
+            ..Default::default()
+        }];
+        let bpe = bpe_for_model("gpt-4").unwrap();
+        assert_eq!(
+            num_tokens_from_functions("gpt-4", &functions).unwrap(),
+            bpe.count_with_special_tokens(&render_function_definitions(&functions)) + 9
+        );
+    }
+}
@@ -0,0 +1,392 @@
+/*!
+ * (de)serializes chat messages in the OpenAI wire format, behind the `serde` feature.
+ */
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::multimodal::{AudioInput, ImageDetail, ImageInput};
+use crate::{ChatCompletionRequestMessage, ContentPart, FunctionCall, MessageContent, ToolCall};
+
+#[derive(Serialize, Deserialize)]
+struct WireMessage {
+    role: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content: Option<WireContent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_call: Option<FunctionCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<ToolCall>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    refusal: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum WireContent {
+    Text(String),
+    Parts(Vec<WireContentPart>),
+}
+
+/// A content part. Images, audio and files are only read, as their dimensions and durations
+/// are not kept.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WireContentPart {
+    Text {
+        text: String,
+    },
+    Refusal {
+        refusal: String,
+    },
+    #[serde(skip_serializing)]
+    ImageUrl {
+        image_url: WireImageUrl,
+    },
+    #[serde(skip_serializing)]
+    InputAudio {
+        input_audio: WireInputAudio,
+    },
+    #[serde(skip_serializing)]
+    File {},
+    #[serde(other, skip_serializing)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct WireImageUrl {
+    #[serde(default)]
+    detail: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WireInputAudio {
+    data: String,
+    format: String,
+}
+
+/// Returns an image part for an image of unknown dimensions, estimated at the highest cost
+/// of its `detail` (see [`ImageInput::unknown_size`]).
+pub(crate) fn image_part(detail: Option<&str>) -> ContentPart {
+    let detail = match detail {
+        Some("low") => ImageDetail::Low,
+        Some("high") => ImageDetail::High,
+        _ => ImageDetail::Auto,
+    };
+    ImageInput::unknown_size(detail).into()
+}
+
+/// Returns an audio part for base64 encoded audio, whose duration is read from its data.
+///
+/// # Errors
+///
+/// Returns an error if the audio is not valid base64, or is not in WAV format.
+pub(crate) fn audio_part(data: &str, format: &str) -> Result<ContentPart> {
+    if format != "wav" {
+        anyhow::bail!(
+            "Cannot read the duration of {} audio, only wav is supported",
+            format
+        )
+    }
+    let bytes = general_purpose::STANDARD.decode(data)?;
+    Ok(AudioInput::from_wav(&bytes)?.into())
+}
+
+/// The error for file parts, whose extracted text and pages are not in the request.
+pub(crate) fn file_part_error() -> anyhow::Error {
+    anyhow!(
+        "Cannot count file inputs from a request, as their text and pages are unknown. \
+         Count them with a FileInput instead."
+    )
+}
+
+impl Serialize for ChatCompletionRequestMessage {
+    /// Serializes a message in the OpenAI wire format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message has images, audio or files, whose data is not kept.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.content.as_ref().is_some_and(MessageContent::has_media) {
+            return Err(ser::Error::custom(
+                "Image, audio and file parts cannot be serialized, as only their size is known",
+            ));
+        }
+        WireMessage {
+            role: self.role.clone(),
+            content: self.content.as_ref().map(wire_content),
+            name: self.name.clone(),
+            function_call: self.function_call.clone(),
+            tool_calls: (!self.tool_calls.is_empty()).then(|| self.tool_calls.clone()),
+            tool_call_id: self.tool_call_id.clone(),
+            refusal: self.refusal.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Writes text content as a string, and content parts as an array, without media parts.
+fn wire_content(content: &MessageContent) -> WireContent {
+    match content {
+        MessageContent::Text(text) => WireContent::Text(text.clone()),
+        MessageContent::Parts(parts) => WireContent::Parts(
+            parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text(text) => Some(WireContentPart::Text { text: text.clone() }),
+                    ContentPart::Refusal(refusal) => Some(WireContentPart::Refusal {
+                        refusal: refusal.clone(),
+                    }),
+                    ContentPart::Image(_) | ContentPart::Audio(_) | ContentPart::File(_) => None,
+                })
+                .collect(),
+        ),
+    }
+}
+
+impl<'de> Deserialize<'de> for ChatCompletionRequestMessage {
+    /// Deserializes a message in the OpenAI wire format.
+    ///
+    /// A string content is read as [`MessageContent::Text`], and a content array as
+    /// [`MessageContent::Parts`] in the same order. Images are estimated at the highest cost of
+    /// their detail, as their dimensions are unknown, and the duration of wav audio is read
+    /// from its data. Parts of unknown types are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for file parts and audio in other formats, which cannot be estimated.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let wire = WireMessage::deserialize(deserializer)?;
+        let content = match wire.content {
+            Some(WireContent::Text(text)) => Some(MessageContent::Text(text)),
+            Some(WireContent::Parts(parts)) => {
+                let mut content_parts = Vec::with_capacity(parts.len());
+                for part in parts {
+                    content_parts.push(match part {
+                        WireContentPart::Text { text } => ContentPart::Text(text),
+                        WireContentPart::Refusal { refusal } => ContentPart::Refusal(refusal),
+                        WireContentPart::ImageUrl { image_url } => {
+                            image_part(image_url.detail.as_deref())
+                        }
+                        WireContentPart::InputAudio { input_audio } => {
+                            audio_part(&input_audio.data, &input_audio.format)
+                                .map_err(de::Error::custom)?
+                        }
+                        WireContentPart::File {} => {
+                            return Err(de::Error::custom(file_part_error()))
+                        }
+                        WireContentPart::Other => continue,
+                    });
+                }
+                Some(MessageContent::Parts(content_parts))
+            }
+            None => None,
+        };
+        Ok(Self {
+            role: wire.role,
+            content,
+            name: wire.name,
+            function_call: wire.function_call,
+            tool_calls: wire.tool_calls.unwrap_or_default(),
+            tool_call_id: wire.tool_call_id,
+            refusal: wire.refusal,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_deserialize_wire_messages() {
+        let messages: Vec<ChatCompletionRequestMessage> = serde_json::from_value(json!([
+            { "role": "system", "content": "You are terse." },
+            {
+                "role": "user",
+                "name": "alice",
+                "content": [
+                    { "type": "text", "text": "What is " },
+                    { "type": "image_url", "image_url": { "url": "https://example.com/a.png" } },
+                    { "type": "text", "text": "this?" }
+                ]
+            },
+            {
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [
+                    {
+                        "id": "call_abc",
+                        "type": "function",
+                        "function": { "name": "lookup", "arguments": "{\"q\":\"a\"}" }
+                    },
+                    {
+                        "id": "call_def",
+                        "type": "custom",
+                        "custom": { "name": "shell", "input": "ls" }
+                    }
+                ]
+            },
+            { "role": "tool", "tool_call_id": "call_abc", "content": "A cat." },
+            {
+                "role": "assistant",
+                "content": [{ "type": "refusal", "refusal": "I can't." }]
+            },
+            {
+                "role": "assistant",
+                "content": [{ "type": "refusal", "refusal": " Sorry." }],
+                "refusal": "I won't."
+            }
+        ]))
+        .unwrap();
+
+        assert_eq!(messages[1].name.as_deref(), Some("alice"));
+        assert_eq!(
+            messages[1].content,
+            Some(MessageContent::Parts(vec![
+                "What is ".into(),
+                ImageInput::unknown_size(ImageDetail::Auto).into(),
+                "this?".into(),
+            ]))
+        );
+        assert_eq!(messages[2].content, None);
+        assert_eq!(
+            messages[2].tool_calls,
+            vec![
+                ToolCall::function("call_abc", "lookup", "{\"q\":\"a\"}"),
+                ToolCall::custom("call_def", "shell", "ls"),
+            ]
+        );
+        assert_eq!(messages[3].name, None);
+        assert_eq!(messages[3].tool_call_id.as_deref(), Some("call_abc"));
+        assert_eq!(
+            messages[4].content,
+            Some(MessageContent::Parts(vec![ContentPart::Refusal(
+                "I can't.".to_string()
+            )]))
+        );
+        assert_eq!(messages[4].refusal, None);
+        assert_eq!(messages[5].refusal.as_deref(), Some("I won't."));
+        assert_eq!(
+            messages[5].content.as_ref().unwrap().parts(),
+            &[ContentPart::Refusal(" Sorry.".to_string())]
+        );
+        assert!(crate::num_tokens_from_messages("gpt-4o", &messages).is_ok());
+    }
+
+    #[test]
+    fn test_serialize_round_trip() {
+        let messages = vec![
+            ChatCompletionRequestMessage {
+                role: "assistant".to_string(),
+                tool_calls: vec![ToolCall::function("call_0", "lookup", "{}")],
+                ..Default::default()
+            },
+            ChatCompletionRequestMessage {
+                role: "tool".to_string(),
+                content: Some("A cat.".into()),
+                tool_call_id: Some("call_0".to_string()),
+                ..Default::default()
+            },
+        ];
+        let value = serde_json::to_value(&messages).unwrap();
+        assert_eq!(
+            value,
+            json!([
+                {
+                    "role": "assistant",
+                    "tool_calls": [{
+                        "type": "function",
+                        "id": "call_0",
+                        "function": { "name": "lookup", "arguments": "{}" }
+                    }]
+                },
+                { "role": "tool", "content": "A cat.", "tool_call_id": "call_0" }
+            ])
+        );
+        let parsed: Vec<ChatCompletionRequestMessage> = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, messages);
+
+        // Content arrays keep the order and type of their parts
+        let value = json!([{
+            "role": "assistant",
+            "content": [
+                { "type": "text", "text": "Maybe " },
+                { "type": "refusal", "refusal": "not." },
+                { "type": "text", "text": " Sorry." }
+            ],
+            "refusal": "No."
+        }]);
+        let parsed: Vec<ChatCompletionRequestMessage> =
+            serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), value);
+    }
+
+    #[test]
+    fn test_deserialize_media_parts() {
+        let parse = |part| {
+            serde_json::from_value::<ChatCompletionRequestMessage>(
+                json!({ "role": "user", "content": [part] }),
+            )
+            .map(|message| message.content.unwrap().parts().to_vec())
+        };
+        let low = json!({ "type": "image_url", "image_url": { "url": "a.png", "detail": "low" } });
+        assert_eq!(
+            parse(low).unwrap(),
+            vec![ImageInput::unknown_size(ImageDetail::Low).into()]
+        );
+
+        // Half a second of 8 kHz mono 8-bit PCM
+        let mut wav = b"RIFF\0\0\0\0WAVEfmt \x10\0\0\0\x01\0\x01\0".to_vec();
+        wav.extend(8_000u32.to_le_bytes());
+        wav.extend(8_000u32.to_le_bytes());
+        wav.extend(b"\x01\0\x08\0data");
+        wav.extend(4_000u32.to_le_bytes());
+        wav.extend([128; 4_000]);
+        let audio = |format| {
+            json!({
+                "type": "input_audio",
+                "input_audio": { "data": general_purpose::STANDARD.encode(&wav), "format": format }
+            })
+        };
+        assert_eq!(
+            parse(audio("wav")).unwrap(),
+            vec![AudioInput::new(std::time::Duration::from_millis(500)).into()]
+        );
+        assert!(parse(audio("mp3")).is_err());
+
+        let file = json!({ "type": "file", "file": { "file_id": "file-abc" } });
+        assert!(parse(file).is_err());
+    }
+
+    #[test]
+    fn test_serialize_content_parts() {
+        let mut message = ChatCompletionRequestMessage {
+            role: "user".to_string(),
+            content: Some(vec!["What is ".into(), "this?".into()].into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            json!({
+                "role": "user",
+                "content": [
+                    { "type": "text", "text": "What is " },
+                    { "type": "text", "text": "this?" }
+                ]
+            })
+        );
+        // Media parts would be silently lost
+        if let Some(MessageContent::Parts(parts)) = &mut message.content {
+            parts.push(ImageInput::new(512, 512).into());
+        }
+        assert!(serde_json::to_value(&message).is_err());
+    }
+}
@@ -1,4 +1,11 @@
-use tiktoken_rs::model::get_context_size;
+use std::sync::{Arc, Mutex};
+
+use tiktoken_rs::model::{
+    clear_deprecation_hook, get_audio_tokens_per_second, get_context_size, get_embedding_limits,
+    get_estimation_profile, get_max_output_tokens, get_model_capabilities, get_model_deprecation,
+    get_model_pricing, set_deprecation_hook, ModelPricing, Provider,
+};
+use tiktoken_rs::tokenizer::Tokenizer;
 
 #[test]
 fn test_finetuned_context_size() {
@@ -71,3 +78,258 @@ fn test_unknown_model_returns_none() {
     assert_eq!(get_context_size("not-a-model"), None);
     assert_eq!(get_context_size(""), None);
 }
+
+#[test]
+fn test_model_pricing() {
+    let gpt4o = get_model_pricing("gpt-4o").unwrap();
+    assert_eq!(gpt4o, ModelPricing::new(2.50, Some(1.25), 10.00));
+    // Dated snapshots resolve to their family
+    assert_eq!(get_model_pricing("gpt-4o-2024-08-06"), Some(gpt4o));
+    // More specific prefixes win over their family
+    assert_eq!(get_model_pricing("gpt-4o-mini").unwrap().input, 0.15);
+    assert_eq!(get_model_pricing("gpt-5-mini").unwrap().input, 0.25);
+    assert_eq!(get_model_pricing("gpt-5.4-mini").unwrap().input, 0.75);
+    assert_eq!(get_model_pricing("o3-mini").unwrap().input, 1.10);
+    assert_eq!(get_model_pricing("o1-pro").unwrap().cached_input, None);
+    // Fine-tuned, open-weight and unknown models have no list price
+    assert_eq!(get_model_pricing("ft:gpt-4o:org:name:id"), None);
+    assert_eq!(get_model_pricing("gpt-oss-120b"), None);
+    assert_eq!(get_model_pricing("foo"), None);
+}
+
+#[test]
+fn test_pricing_estimate() {
+    let pricing = ModelPricing::new(2.00, Some(0.50), 8.00);
+    let cost = pricing.estimate(2_000_000, 1_000_000, 500_000);
+    assert_eq!(cost.input_cost, 2.00);
+    assert_eq!(cost.cached_input_cost, 0.50);
+    assert_eq!(cost.output_cost, 4.00);
+    assert_eq!(cost.total_cost(), 6.50);
+
+    // Without a cached price, cached tokens are billed as regular input
+    let pricing = ModelPricing::new(2.00, None, 8.00);
+    let cost = pricing.estimate(1_000_000, 1_000_000, 0);
+    assert_eq!(cost.total_cost(), 2.00);
+}
+
+#[test]
+fn test_model_capabilities() {
+    let gpt4o = get_model_capabilities("gpt-4o-2024-08-06").unwrap();
+    assert!(gpt4o.chat && gpt4o.tools && gpt4o.vision);
+    assert!(!gpt4o.reasoning && !gpt4o.embeddings);
+
+    let o3_mini = get_model_capabilities("o3-mini").unwrap();
+    assert!(o3_mini.chat && o3_mini.reasoning && o3_mini.tools);
+    assert!(!o3_mini.vision);
+
+    let o1_mini = get_model_capabilities("o1-mini").unwrap();
+    assert!(o1_mini.reasoning && !o1_mini.tools);
+
+    let gpt5 = get_model_capabilities("gpt-5.4-mini").unwrap();
+    assert!(gpt5.reasoning && gpt5.tools && gpt5.vision);
+    assert!(
+        !get_model_capabilities("gpt-5-chat-latest")
+            .unwrap()
+            .reasoning
+    );
+
+    // Same tokenizer as chat models, but not chat models
+    let davinci = get_model_capabilities("davinci-002").unwrap();
+    assert!(davinci.completions && !davinci.chat);
+    let embedding = get_model_capabilities("text-embedding-3-large").unwrap();
+    assert!(embedding.embeddings && !embedding.chat);
+    assert!(
+        !get_model_capabilities("gpt-3.5-turbo-instruct")
+            .unwrap()
+            .chat
+    );
+
+    assert_eq!(
+        get_model_capabilities("ft:gpt-4o-mini:org:name:id"),
+        get_model_capabilities("gpt-4o-mini")
+    );
+    assert_eq!(get_model_capabilities("foo"), None);
+}
+
+#[test]
+fn test_audio_models() {
+    for model in [
+        "gpt-4o-audio-preview",
+        "gpt-4o-mini-audio-preview",
+        "gpt-audio",
+    ] {
+        let capabilities = get_model_capabilities(model).unwrap();
+        assert!(capabilities.chat && capabilities.audio && !capabilities.vision);
+        assert_eq!(get_audio_tokens_per_second(model), Some(10.0));
+        assert_eq!(get_context_size(model), Some(128_000));
+    }
+    assert!(!get_model_capabilities("gpt-4o").unwrap().audio);
+    assert_eq!(get_audio_tokens_per_second("gpt-4o"), None);
+    assert_eq!(
+        get_audio_tokens_per_second("ft:gpt-4o-audio-preview:org"),
+        Some(10.0)
+    );
+}
+
+#[test]
+fn test_embedding_context_size() {
+    assert_eq!(get_context_size("text-embedding-ada-002"), Some(8192));
+    assert_eq!(get_context_size("text-embedding-3-small"), Some(8192));
+    assert_eq!(get_context_size("text-embedding-3-large"), Some(8192));
+    assert_eq!(
+        get_embedding_limits("text-embedding-3-large").map(|l| l.max_input_tokens),
+        get_context_size("text-embedding-3-large")
+    );
+    assert_eq!(get_embedding_limits("gpt-4o"), None);
+}
+
+#[test]
+fn test_max_output_tokens() {
+    assert_eq!(get_max_output_tokens("gpt-5"), Some(128_000));
+    assert_eq!(get_max_output_tokens("gpt-5.4-pro"), Some(128_000));
+    assert_eq!(get_max_output_tokens("o1-mini"), Some(65_536));
+    assert_eq!(get_max_output_tokens("o4-mini"), Some(100_000));
+    assert_eq!(get_max_output_tokens("gpt-4.1-nano"), Some(32_768));
+    assert_eq!(get_max_output_tokens("gpt-4o-mini"), Some(16_384));
+    assert_eq!(get_max_output_tokens("ft:gpt-4o-mini:org"), Some(16_384));
+    assert_eq!(get_max_output_tokens("text-embedding-3-small"), None);
+    // The output limit never exceeds the context window
+    for model in [
+        "gpt-5",
+        "o1",
+        "o3-mini",
+        "gpt-4.1",
+        "gpt-4o",
+        "gpt-4",
+        "gpt-oss-20b",
+    ] {
+        assert!(get_max_output_tokens(model) <= get_context_size(model));
+    }
+}
+
+#[test]
+fn test_model_deprecation() {
+    let davinci = get_model_deprecation("text-davinci-003").unwrap();
+    assert_eq!(davinci.shutdown_date, "2024-01-04");
+    assert_eq!(davinci.replacement, "gpt-3.5-turbo-instruct");
+    assert_eq!(
+        get_model_deprecation("gpt-4-32k-0613").map(|d| d.replacement),
+        Some("gpt-4o")
+    );
+    assert_eq!(
+        get_model_deprecation("ft:davinci:org").map(|d| d.replacement),
+        Some("davinci-002")
+    );
+    // Current models and their successors are not deprecated
+    assert_eq!(get_model_deprecation("davinci-002"), None);
+    assert_eq!(get_model_deprecation("gpt-4o"), None);
+    assert_eq!(get_model_deprecation("gpt-3.5-turbo-0125"), None);
+}
+
+#[test]
+fn test_deprecation_hook() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = seen.clone();
+    set_deprecation_hook(move |model, deprecation| {
+        sink.lock()
+            .unwrap()
+            .push((model.to_string(), deprecation.replacement));
+    });
+
+    tiktoken_rs::bpe_for_model("gpt-4o").unwrap();
+    tiktoken_rs::bpe_for_model("text-davinci-003").unwrap();
+    // Each model is reported once
+    tiktoken_rs::bpe_for_model("text-davinci-003").unwrap();
+    tiktoken_rs::estimate_text_completion_cost("text-davinci-003", "Hello", None, None).ok();
+    let messages = vec![tiktoken_rs::ChatCompletionRequestMessage {
+        role: "user".to_string(),
+        content: Some("Hello".into()),
+        ..Default::default()
+    }];
+    tiktoken_rs::num_tokens_from_messages("gpt-3.5-turbo-0301", &messages).unwrap();
+
+    clear_deprecation_hook();
+    tiktoken_rs::bpe_for_model("text-davinci-002").unwrap();
+
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![
+            ("text-davinci-003".to_string(), "gpt-3.5-turbo-instruct"),
+            ("gpt-3.5-turbo-0301".to_string(), "gpt-3.5-turbo"),
+        ]
+    );
+
+    // A hook can clear itself without deadlocking
+    let calls = Arc::new(Mutex::new(0));
+    let counter = calls.clone();
+    set_deprecation_hook(move |_, _| {
+        *counter.lock().unwrap() += 1;
+        clear_deprecation_hook();
+    });
+    tiktoken_rs::bpe_for_model("text-davinci-003").unwrap();
+    tiktoken_rs::bpe_for_model("gpt-3.5-turbo-0301").unwrap();
+    assert_eq!(*calls.lock().unwrap(), 1);
+}
+
+#[test]
+fn test_estimation_profiles() {
+    let claude = get_estimation_profile("claude-sonnet-4-5-20250929").unwrap();
+    assert_eq!(claude.provider, Provider::Anthropic);
+    assert_eq!(claude.tokenizer, Tokenizer::Cl100kBase);
+    assert_eq!(claude.context_size, 200_000);
+    assert_eq!(claude.max_output_tokens, Some(64_000));
+    assert_eq!(
+        get_estimation_profile("claude-opus-4-1")
+            .unwrap()
+            .max_output_tokens,
+        Some(32_000)
+    );
+    assert_eq!(
+        get_estimation_profile("claude-3-haiku-20240307")
+            .unwrap()
+            .max_output_tokens,
+        Some(4_096)
+    );
+
+    let gemini = get_estimation_profile("gemini-1.5-pro-002").unwrap();
+    assert_eq!(gemini.provider, Provider::Google);
+    assert_eq!(gemini.tokenizer, Tokenizer::O200kBase);
+    assert_eq!(gemini.context_size, 2_097_152);
+
+    assert_eq!(
+        get_estimation_profile("llama-3-8b-instruct")
+            .unwrap()
+            .context_size,
+        8_192
+    );
+    assert_eq!(
+        get_estimation_profile("mistral-large-latest")
+            .unwrap()
+            .provider,
+        Provider::Mistral
+    );
+    assert_eq!(
+        get_estimation_profile("deepseek-reasoner")
+            .unwrap()
+            .provider,
+        Provider::DeepSeek
+    );
+
+    // Estimates are never below the scaled count
+    assert_eq!(claude.estimate(0), 0);
+    assert_eq!(claude.estimate(10), 12);
+    assert_eq!(claude.estimate(11), 14);
+    assert!(claude.estimate_text("Hello, world!") >= 4);
+    // Exact products are not rounded up by floating point noise
+    let mistral = get_estimation_profile("mistral-large-latest").unwrap();
+    assert_eq!(mistral.estimate(50), 55);
+    assert_eq!(mistral.estimate(100), 110);
+    assert_eq!(mistral.estimate(10_000_000), 11_000_000);
+
+    // OpenAI models and unknown models have no profile
+    assert_eq!(get_estimation_profile("gpt-4o"), None);
+    assert_eq!(get_estimation_profile("unknown-model"), None);
+    // The catalog only knows OpenAI models, the limits of the others are in their profile
+    assert_eq!(get_context_size("claude-sonnet-4-5"), None);
+    assert_eq!(get_max_output_tokens("claude-sonnet-4-5"), None);
+}